@@ -0,0 +1,108 @@
+use std::io;
+use std::process::Command;
+
+/// What to do with the chosen window. Runs after the switcher has closed so
+/// it doesn't race the switcher handing activation back.
+pub enum Action {
+    Focus {
+        window_id: String,
+    },
+    /// Move the window onto `workspace`, usually the one the user is on, and
+    /// focus it.
+    MoveHere {
+        window_id: String,
+        workspace: String,
+    },
+    /// Move the window to `workspace` and leave it there unfocused.
+    MoveTo {
+        window_id: String,
+        workspace: String,
+    },
+    /// Toggle the window's fullscreen state and focus it.
+    ToggleFullscreen {
+        window_id: String,
+    },
+    /// Run the `on_select` command, already filled in for the window.
+    Run {
+        window_id: String,
+        command: Vec<String>,
+    },
+    /// Run each in order, stopping at the first failure.
+    Batch(Vec<Action>),
+}
+
+impl Action {
+    pub fn window_ids(&self) -> Vec<&str> {
+        match self {
+            Self::Focus { window_id }
+            | Self::MoveHere { window_id, .. }
+            | Self::MoveTo { window_id, .. }
+            | Self::ToggleFullscreen { window_id }
+            | Self::Run { window_id, .. } => vec![window_id],
+            Self::Batch(actions) => actions.iter().flat_map(Self::window_ids).collect(),
+        }
+    }
+
+    pub fn run(&self, aerospace_path: &str) -> io::Result<()> {
+        let aerospace = |args: &[&str]| aerospace(aerospace_path, args);
+        match self {
+            Self::Focus { window_id } => aerospace(&["focus", "--window-id", window_id]),
+            Self::MoveHere {
+                window_id,
+                workspace,
+            } => {
+                aerospace(&[
+                    "move-node-to-workspace",
+                    "--window-id",
+                    window_id,
+                    workspace,
+                ])?;
+                aerospace(&["focus", "--window-id", window_id])
+            }
+            Self::MoveTo {
+                window_id,
+                workspace,
+            } => aerospace(&[
+                "move-node-to-workspace",
+                "--window-id",
+                window_id,
+                workspace,
+            ]),
+            Self::ToggleFullscreen { window_id } => {
+                aerospace(&["fullscreen", "--window-id", window_id])?;
+                aerospace(&["focus", "--window-id", window_id])
+            }
+            Self::Run { command, .. } => {
+                let Some((program, args)) = command.split_first() else {
+                    return Ok(());
+                };
+                verbose!("running {}", command.join(" "));
+                let status = Command::new(program).args(args).status()?;
+                verbose!("{} exited with {}", program, status);
+                if !status.success() {
+                    return Err(io::Error::other(format!(
+                        "{} exited with {}",
+                        program, status
+                    )));
+                }
+                Ok(())
+            }
+            Self::Batch(actions) => actions
+                .iter()
+                .try_for_each(|action| action.run(aerospace_path)),
+        }
+    }
+}
+
+fn aerospace(aerospace_path: &str, args: &[&str]) -> io::Result<()> {
+    verbose!("running {} {}", aerospace_path, args.join(" "));
+    let status = Command::new(aerospace_path).args(args).status()?;
+    verbose!("{} {} exited with {}", aerospace_path, args[0], status);
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "aerospace {} exited with {}",
+            args[0], status
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Replaces `~/.cache/aerospace-window-switcher` when set.
+static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Points every cache file at `dir`, e.g. to keep tests out of the real home.
+#[cfg(test)]
+pub fn set_dir(dir: PathBuf) {
+    *DIR.lock().unwrap() = Some(dir);
+}
+
+pub fn path(name: &str) -> Option<PathBuf> {
+    if let Some(dir) = &*DIR.lock().unwrap() {
+        return Some(dir.join(name));
+    }
+    let home = std::env::var_os("HOME")?;
+    Some(
+        PathBuf::from(home)
+            .join(".cache/aerospace-window-switcher")
+            .join(name),
+    )
+}
+
+/// Reads a JSON cache file, treating a missing or corrupt file as empty.
+pub fn load<T: DeserializeOwned>(name: &str) -> Option<T> {
+    let contents = std::fs::read_to_string(path(name)?).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+pub fn save<T: Serialize>(name: &str, value: &T) {
+    let Some(path) = path(name) else {
+        return;
+    };
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| {
+            let json = serde_json::to_string(value).map_err(std::io::Error::other)?;
+            std::fs::write(&path, json)
+        });
+    if let Err(e) = result {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::theme::Theme;
+
+pub const USAGE: &str = "\
+Usage: aerospace-window-switcher [options] [query]
+
+Options:
+  --workspace <name>  only list windows on the given workspace (e.g. `focused`)
+  --monitor <id>      only list windows on the given monitor (an id, `focused` or `mouse`)
+  --print             print the chosen window id to stdout instead of focusing it
+  --stdin             read `id | app | title` lines from stdin instead of asking aerospace
+  --theme light|dark  color scheme, defaults to dark
+  --auto-select       focus the only window matching the query without showing the switcher
+  --back              refocus the window that was focused before the last switch, and exit
+  --verbose           log aerospace commands and results to stderr
+  -h, --help          print this help and exit
+  -V, --version       print the version and exit
+";
+
+/// Command-line flags. Everything is optional; with no flags the switcher
+/// behaves exactly as it always has.
+#[derive(Default)]
+pub struct Args {
+    pub workspace: Option<String>,
+    pub monitor: Option<String>,
+    /// Print the chosen window id to stdout instead of focusing it.
+    pub print: bool,
+    /// Read the window list from stdin instead of aerospace.
+    pub stdin: bool,
+    pub theme: Theme,
+    /// Starts the search box with this instead of being empty.
+    pub query: Option<String>,
+    /// Focus the only match straight away, never showing the window.
+    pub auto_select: bool,
+    /// Undo the last switch instead of opening the switcher.
+    pub back: bool,
+    /// Log aerospace commands and their results to stderr.
+    pub verbose: bool,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        let mut args = std::env::args().skip(1);
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--workspace" => {
+                    parsed.workspace =
+                        Some(args.next().ok_or("--workspace requires a workspace name")?);
+                }
+                "--monitor" => {
+                    parsed.monitor = Some(args.next().ok_or("--monitor requires a monitor id")?);
+                }
+                "--print" => parsed.print = true,
+                "--stdin" => parsed.stdin = true,
+                "--auto-select" => parsed.auto_select = true,
+                "--back" => parsed.back = true,
+                "--verbose" => parsed.verbose = true,
+                "-h" | "--help" => parsed.help = true,
+                "-V" | "--version" => parsed.version = true,
+                "--theme" => {
+                    let name = args.next().ok_or("--theme requires light or dark")?;
+                    parsed.theme =
+                        Theme::parse(&name).ok_or_else(|| format!("unknown theme: {}", name))?;
+                }
+                _ if arg.starts_with('-') => {
+                    return Err(format!("unknown argument: {} (see --help)", arg))
+                }
+                // Unquoted words are one query, e.g. `google chrome`.
+                _ => match &mut parsed.query {
+                    Some(query) => {
+                        query.push(' ');
+                        query.push_str(&arg);
+                    }
+                    None => parsed.query = Some(arg),
+                },
+            }
+        }
+        Ok(parsed)
+    }
+}
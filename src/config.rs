@@ -0,0 +1,312 @@
+use crate::keys::Keybindings;
+use crate::theme::Theme;
+use eframe::egui;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    #[default]
+    Fuzzy,
+    /// Plain case-insensitive substring, earlier matches ranked higher.
+    Substring,
+}
+
+/// Case handling of fuzzy matching.
+#[derive(Clone, Copy, Default, Deserialize)]
+pub enum CaseMode {
+    #[serde(rename = "ignore_case")]
+    Ignore,
+    /// Case-insensitive unless the query has an uppercase letter.
+    #[default]
+    #[serde(rename = "smart_case")]
+    Smart,
+    #[serde(rename = "respect_case")]
+    Respect,
+}
+
+/// How the list is ordered before anything is typed.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptySort {
+    /// As `aerospace list-windows` prints them.
+    Aerospace,
+    /// By workspace, numerically where possible, then app name.
+    Workspace,
+    /// Most recently focused through the switcher first.
+    #[default]
+    Mru,
+}
+
+/// How the selected row stands out.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionStyle {
+    /// The whole row in the accent color.
+    #[default]
+    Fill,
+    /// An accent bar on the left edge over a faint tint.
+    Bar,
+}
+
+/// Where the window opens.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpawnAt {
+    Center,
+    /// Centered under the mouse pointer, kept on its screen.
+    Cursor,
+    /// Where it was last closed.
+    #[default]
+    Saved,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub window_width: f32,
+    pub window_height: f32,
+    pub max_list_height: f32,
+    pub item_height: f32,
+    pub search_box_height: f32,
+    pub spawn_at: SpawnAt,
+    /// Size of the monospace font used for the query and the rows.
+    pub font_size: f32,
+    /// Overrides the theme's selection color.
+    pub accent_color: Option<String>,
+    pub selection_style: SelectionStyle,
+    /// Alpha of the panel background, 0 (transparent) to 255 (solid).
+    pub background_opacity: u8,
+    /// How long to wait for aerospace before giving up on the window list.
+    pub load_timeout_secs: f32,
+    /// Show the selected window's full details below the list.
+    pub show_preview: bool,
+    /// App names to hide, case-insensitive; `*` and `?` work as in globs.
+    pub exclude_apps: Vec<String>,
+    /// Restore the previous query on launch, selected so typing replaces it.
+    pub remember_query: bool,
+    /// App names, case-insensitive, whose windows always sort to the top.
+    pub pinned: Vec<String>,
+    pub match_mode: MatchMode,
+    pub case_mode: CaseMode,
+    pub empty_sort: EmptySort,
+    /// Fuzzy matches scoring below this are dropped.
+    pub min_score: i64,
+    /// Fuzzy matches scoring below this fraction of the best match are dropped.
+    pub min_score_ratio: f32,
+    /// Multiplies the score of a match in the app name.
+    pub name_weight: f32,
+    /// Multiplies the score of a match in the window title.
+    pub info_weight: f32,
+    /// How much a window's use count, weighted by recency, adds to its
+    /// fuzzy score.
+    pub frecency_weight: f32,
+    /// Stay hidden until the window list is in rather than flash "Loading...".
+    pub defer_show: bool,
+    /// Only the best this many matches are listed.
+    pub max_results: Option<usize>,
+    /// Apply `max_results` before anything is typed too.
+    pub limit_empty_query: bool,
+    /// Close when another window takes focus, like Spotlight.
+    pub close_on_blur: bool,
+    /// Start with one row per app instead of one per window.
+    pub compact: bool,
+    /// Pad app names to one width so the titles after them line up.
+    pub align_names: bool,
+    /// With `align_names`, names longer than this many chars are cut.
+    pub name_column_width: Option<usize>,
+    /// Give each app name a color of its own, derived from the name.
+    pub colorize_apps: bool,
+    /// Append each window's app bundle id, dimmed, to its row.
+    pub show_bundle_id: bool,
+    /// Start with the icon grid instead of the list.
+    pub grid: bool,
+    /// Escape first clears a non-empty query, and only closes once it's empty.
+    pub escape_clears_query: bool,
+    /// Focus a window with a single click. When off, a click selects and a
+    /// double-click (or Enter) focuses.
+    pub click_to_focus: bool,
+    /// Alt-tab style: launching again within 400ms moves the open
+    /// switcher's selection down instead of opening another.
+    pub cycle_on_relaunch: bool,
+    /// Command run for the chosen window instead of focusing it, with
+    /// `{id}`, `{name}`, `{title}`, `{workspace}` and `{monitor}` filled in.
+    pub on_select: Option<String>,
+    /// The aerospace binary, either a name looked up on `PATH` or a path.
+    pub aerospace_path: String,
+    pub keybindings: Keybindings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window_width: 500.0,
+            window_height: 400.0,
+            max_list_height: 400.0,
+            item_height: 28.0,
+            search_box_height: 32.0,
+            spawn_at: SpawnAt::Saved,
+            font_size: 12.0,
+            accent_color: None,
+            selection_style: SelectionStyle::Fill,
+            background_opacity: 0,
+            load_timeout_secs: 2.0,
+            show_preview: true,
+            exclude_apps: Vec::new(),
+            remember_query: false,
+            pinned: Vec::new(),
+            match_mode: MatchMode::Fuzzy,
+            case_mode: CaseMode::Smart,
+            empty_sort: EmptySort::Mru,
+            min_score: 0,
+            min_score_ratio: 0.25,
+            name_weight: 1.0,
+            info_weight: 1.0,
+            frecency_weight: 1.0,
+            defer_show: false,
+            max_results: None,
+            limit_empty_query: false,
+            close_on_blur: true,
+            compact: false,
+            align_names: false,
+            name_column_width: None,
+            colorize_apps: false,
+            show_bundle_id: false,
+            grid: false,
+            escape_clears_query: false,
+            click_to_focus: true,
+            cycle_on_relaunch: false,
+            on_select: None,
+            aerospace_path: "aerospace".to_string(),
+            keybindings: Keybindings::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+        Some(PathBuf::from(home).join(".config/aerospace-window-switcher/config.toml"))
+    }
+
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(_) => return Self::default(),
+        };
+
+        match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to parse {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// `load`, with `AEROSPACE_BIN` taking precedence over `aerospace_path`
+    /// so a test or wrapper can point the switcher at another binary.
+    pub fn from_env() -> Self {
+        let mut config = Self::load();
+        if let Ok(bin) = std::env::var("AEROSPACE_BIN") {
+            config.aerospace_path = bin;
+        }
+        config
+    }
+
+    pub fn is_excluded(&self, app_name: &str) -> bool {
+        let app_name = app_name.to_lowercase();
+        self.exclude_apps
+            .iter()
+            .any(|pattern| glob_match(&pattern.to_lowercase(), &app_name))
+    }
+
+    /// Checks `aerospace_path` up front so a typo gets a clear message
+    /// rather than an empty list.
+    pub fn check_aerospace_path(&self) -> Result<(), String> {
+        let path = Path::new(&self.aerospace_path);
+        if path.components().count() > 1 {
+            return if path.is_file() {
+                Ok(())
+            } else {
+                Err(format!("aerospace_path {} does not exist", path.display()))
+            };
+        }
+
+        let on_path = std::env::var_os("PATH")
+            .is_some_and(|dirs| std::env::split_paths(&dirs).any(|dir| dir.join(path).is_file()));
+        if on_path {
+            Ok(())
+        } else {
+            Err(format!("{} not found on PATH", self.aerospace_path))
+        }
+    }
+
+    pub fn load_timeout(&self) -> std::time::Duration {
+        std::time::Duration::try_from_secs_f32(self.load_timeout_secs).unwrap_or_else(|_| {
+            eprintln!(
+                "Invalid load_timeout_secs {}, using default",
+                self.load_timeout_secs
+            );
+            std::time::Duration::from_secs_f32(Self::default().load_timeout_secs)
+        })
+    }
+
+    pub fn accent_color(&self, theme: Theme) -> egui::Color32 {
+        let Some(hex) = &self.accent_color else {
+            return theme.selection_fill();
+        };
+        parse_hex_color(hex).unwrap_or_else(|| {
+            eprintln!("Invalid accent_color {:?}, using default", hex);
+            theme.selection_fill()
+        })
+    }
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`, with `aa` as plain (unmultiplied) alpha
+/// like in CSS.
+fn parse_hex_color(hex: &str) -> Option<egui::Color32> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some(egui::Color32::from_rgba_unmultiplied(
+        channel(0)?,
+        channel(2)?,
+        channel(4)?,
+        alpha,
+    ))
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was seen, and how much text it has swallowed.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
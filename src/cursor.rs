@@ -0,0 +1,52 @@
+use std::process::Command;
+
+/// Asks AppKit, through JavaScript for Automation, for the pointer and the
+/// frame of the screen it's on. Cocoa counts y up from the bottom of the
+/// primary screen, so both are flipped to the top-left origin winit uses.
+const SCRIPT: &str = r#"
+ObjC.import("AppKit");
+const p = $.NSEvent.mouseLocation;
+const screens = $.NSScreen.screens;
+const top = screens.objectAtIndex(0).frame.size.height;
+let f = screens.objectAtIndex(0).frame;
+for (let i = 0; i < screens.count; i++) {
+    const s = screens.objectAtIndex(i).frame;
+    if (p.x >= s.origin.x && p.x < s.origin.x + s.size.width
+        && p.y >= s.origin.y && p.y < s.origin.y + s.size.height) {
+        f = s;
+    }
+}
+[p.x, top - p.y, f.origin.x, top - f.origin.y - f.size.height, f.size.width, f.size.height].join(" ");
+"#;
+
+/// Where to put a `width` x `height` window so it opens centered under the
+/// pointer, kept fully on the pointer's screen.
+pub fn window_position(width: f32, height: f32) -> Option<(f32, f32)> {
+    let output = Command::new("osascript")
+        .args(["-l", "JavaScript", "-e", SCRIPT])
+        .output()
+        .map_err(|e| eprintln!("Failed to run osascript: {}", e))
+        .ok()?;
+    if !output.status.success() {
+        eprintln!(
+            "Failed to get the pointer position: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+
+    let values: Vec<f32> = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    let &[x, y, screen_x, screen_y, screen_width, screen_height] = values.as_slice() else {
+        return None;
+    };
+    let clamp = |value: f32, start: f32, size: f32, extent: f32| {
+        value.min(start + extent - size).max(start)
+    };
+    Some((
+        clamp(x - width / 2.0, screen_x, width, screen_width),
+        clamp(y - height / 2.0, screen_y, height, screen_height),
+    ))
+}
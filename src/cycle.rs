@@ -0,0 +1,102 @@
+use crate::cache;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CACHE: &str = "cycle.json";
+/// A relaunch this soon after the previous press is taken as the hotkey
+/// being pressed again rather than a fresh open.
+const CYCLE_WINDOW_MS: u64 = 400;
+/// Leeway past `CYCLE_WINDOW_MS` for a relaunch still starting up.
+const LISTEN_SLACK_MS: u64 = 200;
+
+/// The last hotkey press, shared between the open switcher and relaunches.
+#[derive(Default, Serialize, Deserialize)]
+struct Press {
+    /// Unix milliseconds.
+    at: u64,
+    /// Presses forwarded to the open switcher since it launched.
+    count: u32,
+    /// Pid of the switcher taking forwarded presses, cleared when it stops.
+    #[serde(default)]
+    listener: Option<u32>,
+}
+
+/// Records this launch as a hotkey press. Returns true if a switcher opened
+/// moments ago should advance its selection instead, in which case this
+/// process has nothing left to do.
+pub fn forward_press() -> bool {
+    let previous: Press = cache::load(CACHE).unwrap_or_default();
+    let now = now();
+    // A switcher closed within the window can't take the press.
+    let forward = previous.listener.is_some() && now.saturating_sub(previous.at) < CYCLE_WINDOW_MS;
+    let press = if forward {
+        Press {
+            at: now,
+            count: previous.count + 1,
+            listener: previous.listener,
+        }
+    } else {
+        // This launch opens the switcher that listens for the next press.
+        Press {
+            at: now,
+            count: 0,
+            listener: Some(std::process::id()),
+        }
+    };
+    cache::save(CACHE, &press);
+    forward
+}
+
+/// Watches for presses forwarded by `forward_press`.
+#[derive(Default)]
+pub struct Listener {
+    /// Presses read so far.
+    seen: u32,
+    /// Presses already handed out by `take`.
+    taken: u32,
+}
+
+impl Listener {
+    /// Reads presses forwarded since the last poll. Returns false once the
+    /// last one is too old for another to be forwarded.
+    pub fn poll(&mut self) -> bool {
+        let Some(press) = cache::load::<Press>(CACHE) else {
+            return false;
+        };
+        // Another switcher launched since; presses go to it now.
+        if press.listener != Some(std::process::id()) {
+            return false;
+        }
+        self.seen = self.seen.max(press.count);
+        now().saturating_sub(press.at) < CYCLE_WINDOW_MS + LISTEN_SLACK_MS
+    }
+
+    /// How many read presses haven't been acted on yet.
+    pub fn pending(&self) -> u32 {
+        self.seen - self.taken
+    }
+
+    /// Hands out the pending presses, marking them acted on.
+    pub fn take(&mut self) -> u32 {
+        let pending = self.pending();
+        self.taken = self.seen;
+        pending
+    }
+
+    /// Stops presses being forwarded here, e.g. once the switcher closes.
+    pub fn stop(&self) {
+        let Some(mut press) = cache::load::<Press>(CACHE) else {
+            return;
+        };
+        if press.listener == Some(std::process::id()) {
+            press.listener = None;
+            cache::save(CACHE, &press);
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
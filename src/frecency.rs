@@ -0,0 +1,61 @@
+use crate::cache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CACHE: &str = "frecency.json";
+/// Entries not used for this long are forgotten; window ids don't outlive
+/// a login session for long anyway.
+const MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    count: u32,
+    /// Unix seconds.
+    last_used: u64,
+}
+
+/// How often and how recently each window id was focused.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Frecency(HashMap<String, Entry>);
+
+impl Frecency {
+    pub fn load() -> Self {
+        cache::load(CACHE).unwrap_or_default()
+    }
+
+    pub fn record(&mut self, id: &str) {
+        let now = now();
+        self.0
+            .retain(|_, entry| now.saturating_sub(entry.last_used) < MAX_AGE_SECS);
+        let entry = self.0.entry(id.to_string()).or_insert(Entry {
+            count: 0,
+            last_used: now,
+        });
+        entry.count += 1;
+        entry.last_used = now;
+        cache::save(CACHE, self);
+    }
+
+    /// Use count weighted by recency, Firefox style: a use within the hour
+    /// counts 4x, within the day 2x, within the week 1x, older 0.5x.
+    pub fn score(&self, id: &str) -> f32 {
+        let Some(entry) = self.0.get(id) else {
+            return 0.0;
+        };
+        let age = now().saturating_sub(entry.last_used);
+        let weight = match age {
+            0..3_600 => 4.0,
+            3_600..86_400 => 2.0,
+            86_400..604_800 => 1.0,
+            _ => 0.5,
+        };
+        entry.count as f32 * weight
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
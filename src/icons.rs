@@ -0,0 +1,73 @@
+use crate::cache;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Icons are downscaled to twice the row size so they stay crisp on Retina.
+const ICON_PIXELS: &str = "40";
+
+/// An app name with its icon, if one could be found.
+pub type LoadedIcon = (String, Option<egui::ColorImage>);
+
+/// Finds the app bundle for `app_name` and returns its icon, converting the
+/// `.icns` to a PNG in the cache dir on first use. Returns `None` off macOS or
+/// when the app has no classic icon file.
+pub fn load(app_name: &str) -> Option<egui::ColorImage> {
+    let png = cache::path("icons")?.join(format!("{}.png", app_name.replace('/', "_")));
+    if !png.exists() {
+        let icns = find_icns(&find_bundle(app_name)?)?;
+        std::fs::create_dir_all(png.parent()?).ok()?;
+        let status = Command::new("sips")
+            .args(["-s", "format", "png", "-Z", ICON_PIXELS])
+            .arg(&icns)
+            .arg("--out")
+            .arg(&png)
+            .output()
+            .ok()?
+            .status;
+        if !status.success() {
+            return None;
+        }
+    }
+
+    let image = image::open(&png).ok()?.to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    Some(egui::ColorImage::from_rgba_unmultiplied(
+        size,
+        image.as_flat_samples().as_slice(),
+    ))
+}
+
+fn find_bundle(app_name: &str) -> Option<PathBuf> {
+    // The name is spliced into a Spotlight query, so refuse to quote-escape.
+    if app_name.contains(['\'', '"', '\\']) {
+        return None;
+    }
+
+    let query = format!(
+        "kMDItemContentType == 'com.apple.application-bundle' && kMDItemFSName == '{}.app'",
+        app_name
+    );
+    let output = Command::new("mdfind").arg(query).output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next().map(PathBuf::from)
+}
+
+fn find_icns(bundle: &Path) -> Option<PathBuf> {
+    let output = Command::new("defaults")
+        .arg("read")
+        .arg(bundle.join("Contents/Info"))
+        .arg("CFBundleIconFile")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let mut file = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !file.ends_with(".icns") {
+        file.push_str(".icns");
+    }
+    let icns = bundle.join("Contents/Resources").join(file);
+    icns.exists().then_some(icns)
+}
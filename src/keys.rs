@@ -0,0 +1,145 @@
+use eframe::egui;
+use serde::Deserialize;
+
+/// A key plus the exact modifiers that must be held, e.g. `ctrl+n`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct KeySpec {
+    modifiers: egui::Modifiers,
+    key: egui::Key,
+}
+
+impl KeySpec {
+    const fn new(modifiers: egui::Modifiers, key: egui::Key) -> Self {
+        Self { modifiers, key }
+    }
+
+    /// Parses `+` separated modifiers (`ctrl`, `alt`, `shift`, `cmd`)
+    /// followed by an egui key name such as `down`, `tab`, `enter` or `n`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+        let key_name = parts.pop().unwrap_or_default();
+        let key = egui::Key::from_name(key_name)
+            .or_else(|| {
+                egui::Key::ALL
+                    .iter()
+                    .copied()
+                    .find(|key| key.name().eq_ignore_ascii_case(key_name))
+            })
+            .ok_or_else(|| format!("unknown key {:?} in {:?}", key_name, spec))?;
+
+        let mut modifiers = egui::Modifiers::NONE;
+        for part in parts {
+            match part.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "alt" | "opt" | "option" => modifiers.alt = true,
+                "shift" => modifiers.shift = true,
+                "cmd" | "command" => modifiers.mac_cmd = true,
+                _ => return Err(format!("unknown modifier {:?} in {:?}", part, spec)),
+            }
+        }
+        Ok(Self::new(modifiers, key))
+    }
+
+    /// Unlike `consume_key`, extra Shift or Alt don't match, so `tab` and
+    /// `shift+tab` can be bound to different actions.
+    fn matches(self, event: &egui::Event) -> bool {
+        matches!(
+            event,
+            egui::Event::Key { key, pressed: true, modifiers, .. }
+                if *key == self.key && modifiers.matches_exact(self.modifiers)
+        )
+    }
+}
+
+impl TryFrom<String> for KeySpec {
+    type Error = String;
+
+    fn try_from(spec: String) -> Result<Self, String> {
+        Self::parse(&spec)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum KeyAction {
+    Down,
+    Up,
+    Select,
+    Close,
+    Cancel,
+}
+
+/// The `[keybindings]` config table. Each action takes a list of keys;
+/// actions left out keep their defaults.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Keybindings {
+    pub down: Vec<KeySpec>,
+    pub up: Vec<KeySpec>,
+    pub select: Vec<KeySpec>,
+    /// Close the selected window.
+    pub close: Vec<KeySpec>,
+    /// Dismiss the switcher.
+    pub cancel: Vec<KeySpec>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        use egui::{Key, Modifiers};
+        Self {
+            down: vec![
+                KeySpec::new(Modifiers::NONE, Key::ArrowDown),
+                KeySpec::new(Modifiers::NONE, Key::Tab),
+                KeySpec::new(Modifiers::CTRL, Key::N),
+                KeySpec::new(Modifiers::CTRL, Key::J),
+            ],
+            up: vec![
+                KeySpec::new(Modifiers::NONE, Key::ArrowUp),
+                KeySpec::new(Modifiers::SHIFT, Key::Tab),
+                KeySpec::new(Modifiers::CTRL, Key::P),
+                KeySpec::new(Modifiers::CTRL, Key::K),
+            ],
+            select: vec![KeySpec::new(Modifiers::NONE, Key::Enter)],
+            close: vec![KeySpec::new(Modifiers::CTRL, Key::W)],
+            cancel: vec![KeySpec::new(Modifiers::NONE, Key::Escape)],
+        }
+    }
+}
+
+impl Keybindings {
+    fn actions(&self) -> [(KeyAction, &[KeySpec]); 5] {
+        [
+            (KeyAction::Down, &self.down),
+            (KeyAction::Up, &self.up),
+            (KeyAction::Select, &self.select),
+            (KeyAction::Close, &self.close),
+            (KeyAction::Cancel, &self.cancel),
+        ]
+    }
+
+    fn action_for(&self, event: &egui::Event) -> Option<KeyAction> {
+        self.actions()
+            .into_iter()
+            .find(|(_, specs)| specs.iter().any(|spec| spec.matches(event)))
+            .map(|(action, _)| action)
+    }
+
+    /// The first bound action pressed this frame, with the key event that
+    /// triggered it. The event is consumed so the search box doesn't also
+    /// act on it.
+    pub fn consume(&self, input: &mut egui::InputState) -> Option<(KeyAction, egui::Event)> {
+        let position = input
+            .events
+            .iter()
+            .position(|event| self.action_for(event).is_some())?;
+        let event = input.events.remove(position);
+        Some((self.action_for(&event)?, event))
+    }
+
+    pub fn any_pressed(&self, input: &egui::InputState) -> bool {
+        input
+            .events
+            .iter()
+            .any(|event| self.action_for(event).is_some())
+    }
+}
@@ -0,0 +1,20 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+pub fn verbose() -> bool {
+    VERBOSE.load(Ordering::Relaxed)
+}
+
+/// `eprintln!` that only prints with `--verbose`.
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if crate::log::verbose() {
+            eprintln!($($arg)*);
+        }
+    };
+}
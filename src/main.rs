@@ -1,215 +1,1803 @@
+#[macro_use]
+mod log;
+
+mod actions;
+mod cache;
+mod cli;
+mod config;
+mod cursor;
+mod cycle;
+mod frecency;
+mod icons;
+mod keys;
+mod theme;
+
+use actions::Action;
+use cli::Args;
+use config::{CaseMode, Config, EmptySort, MatchMode, SelectionStyle, SpawnAt};
 use eframe::egui;
+use frecency::Frecency;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
-use std::io::{BufRead, BufReader};
+use keys::KeyAction;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::process::{Command, Stdio};
-use std::sync::{Mutex, Arc};
+use std::sync::{Arc, Mutex};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
 
-const SEARCH_BOX_HEIGHT: f32 = 32.0;
-const ITEM_HEIGHT: f32 = 28.0;
-const MAX_LIST_HEIGHT: f32 = 400.0;
+const ICON_SIZE: f32 = 20.0;
+const GRID_CELL_SIZE: f32 = 64.0;
+const GRID_ICON_SIZE: f32 = 44.0;
+/// Space between the location tag and the row's right edge.
+const TAG_MARGIN: f32 = 8.0;
+const SELECTION_BAR_WIDTH: f32 = 3.0;
+const PREVIEW_HEIGHT: f32 = 72.0;
+const STATUS_LINE_HEIGHT: f32 = 16.0;
 const PADDING_TOP: f32 = 8.0;
+/// Tab separated, since the title (last, so it may contain anything) and
+/// even app names can contain `|`.
+const LIST_WINDOWS_FORMAT: &str =
+    "%{window-id}%{tab}%{workspace}%{tab}%{monitor-id}%{tab}%{app-bundle-id}%{tab}%{app-name}%{tab}%{window-title}";
+const WINDOWS_CACHE: &str = "windows.json";
+const MRU_CACHE: &str = "mru.json";
+const POSITION_CACHE: &str = "position.json";
+const QUERY_CACHE: &str = "query.json";
+const PINNED_CACHE: &str = "pinned.json";
+const HISTORY_CACHE: &str = "history.json";
+/// The workspace the switcher was last opened on, and the one before it.
+const WORKSPACES_CACHE: &str = "workspaces.json";
+/// The window focused before the last switch, for `--back`.
+const BACK_CACHE: &str = "back.json";
+const MRU_LEN: usize = 50;
+const HISTORY_LEN: usize = 50;
+/// Id matches count for less so digits in a query don't drown out names.
+const ID_SCORE_PERCENT: i64 = 75;
+/// Added per matched char that starts a word.
+const WORD_START_BONUS: i64 = 8;
+/// Below this many windows, scoring on one thread beats spawning more.
+const PARALLEL_MIN_WINDOWS: usize = 300;
+/// The frecency boost is `FRECENCY_BOOST_SCALE * ln(1 + frecency)`, at most
+/// `FRECENCY_MAX_BOOST`, times `frecency_weight`: about the gap between
+/// two close fuzzy matches.
+const FRECENCY_BOOST_SCALE: f32 = 6.0;
+const FRECENCY_MAX_BOOST: f32 = 30.0;
+/// A substring match at the very start; each char further in costs a point.
+const SUBSTRING_MAX_SCORE: i64 = 1000;
+/// Lists longer than this re-filter once typing pauses, not per keystroke.
+const DEBOUNCE_MIN_WINDOWS: usize = 200;
+const DEBOUNCE_DELAY: std::time::Duration = std::time::Duration::from_millis(30);
+const FETCH_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+const FETCH_ATTEMPTS: u32 = 3;
+/// Doubled after the first retry.
+const FETCH_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+const FLASH_DURATION: std::time::Duration = std::time::Duration::from_millis(1500);
 
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct WindowInfo {
     id: String,
     name: String,
     info: String,
+    workspace: String,
+    monitor: String,
+    /// E.g. `com.apple.Safari`, telling apart apps with the same name.
+    /// Empty before aerospace reports it, or from caches that predate it.
+    #[serde(default)]
+    bundle_id: String,
+    /// `name` and `info` with diacritics folded, filled in by `prepare_search`.
+    #[serde(skip)]
+    search_name: String,
+    #[serde(skip)]
+    search_info: String,
+}
+
+impl WindowInfo {
+    fn prepare_search(&mut self) {
+        self.search_name = fold_diacritics(&self.name);
+        self.search_info = fold_diacritics(&self.info);
+    }
+
+    /// Parses a line produced by `LIST_WINDOWS_FORMAT`, or the legacy
+    /// `id | app | title` output when `with_location` is false. Lines whose
+    /// id isn't all digits are dropped, since the id is passed on to aerospace.
+    fn parse(line: &str, with_location: bool) -> Option<Self> {
+        let window = Self::parse_fields(line, with_location)?;
+        let valid_id = !window.id.is_empty() && window.id.bytes().all(|b| b.is_ascii_digit());
+        valid_id.then_some(window)
+    }
+
+    fn parse_fields(line: &str, with_location: bool) -> Option<Self> {
+        if with_location {
+            let parts: Vec<&str> = line.splitn(6, '\t').collect();
+            if parts.len() < 6 {
+                return None;
+            }
+            Some(Self {
+                id: parts[0].trim().to_string(),
+                workspace: parts[1].trim().to_string(),
+                monitor: parts[2].trim().to_string(),
+                bundle_id: parts[3].trim().to_string(),
+                name: parts[4].trim().to_string(),
+                info: parts[5].trim().to_string(),
+                ..Default::default()
+            })
+        } else {
+            let parts: Vec<&str> = line.splitn(3, '|').collect();
+            if parts.len() < 3 {
+                return None;
+            }
+            Some(Self {
+                id: parts[0].trim().to_string(),
+                name: parts[1].trim().to_string(),
+                info: parts[2].trim().to_string(),
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// Entries of the Ctrl+Space action menu, acting on the selected window.
+#[derive(Clone, Copy)]
+enum MenuEntry {
+    Focus,
+    MoveHere,
+    ToggleFullscreen,
+    Close,
+}
+
+impl MenuEntry {
+    const ALL: [Self; 4] = [
+        Self::Focus,
+        Self::MoveHere,
+        Self::ToggleFullscreen,
+        Self::Close,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Focus => "Focus",
+            Self::MoveHere => "Move to current workspace",
+            Self::ToggleFullscreen => "Toggle fullscreen",
+            Self::Close => "Close window",
+        }
+    }
+}
+
+/// Which fields a fuzzy query is scored against.
+#[derive(Clone, Copy, PartialEq)]
+enum SearchField {
+    Any,
+    App,
+    Title,
+}
+
+impl SearchField {
+    /// Splits an `app:` or `title:` prefix off the query.
+    fn parse(query: &str) -> (Self, &str) {
+        if let Some(rest) = query.strip_prefix("app:") {
+            (Self::App, rest.trim_start())
+        } else if let Some(rest) = query.strip_prefix("title:") {
+            (Self::Title, rest.trim_start())
+        } else {
+            (Self::Any, query)
+        }
+    }
+}
+
+/// What ranking needs from the switcher besides the windows and the query.
+struct Ranking<'a> {
+    config: &'a Config,
+    mru: &'a [String],
+    frecency: &'a Frecency,
+    matcher: &'a SkimMatcherV2,
+    /// For substring matching; the fuzzy `matcher` has its own case mode.
+    case_sensitive: bool,
+}
+
+/// What the background fetch thread hands back to the UI.
+struct FetchResult {
+    /// A message for the user if aerospace couldn't be run.
+    windows: Result<Vec<WindowInfo>, String>,
+    focused_workspace: Option<String>,
+    focused_window: Option<String>,
+}
+
+/// A window that survived filtering, with the char positions the query
+/// matched in whichever field scored best.
+struct MatchedWindow {
+    index: usize,
+    name_indices: Vec<usize>,
+    info_indices: Vec<usize>,
+}
+
+impl MatchedWindow {
+    fn unmatched(index: usize) -> Self {
+        Self {
+            index,
+            name_indices: Vec::new(),
+            info_indices: Vec::new(),
+        }
+    }
 }
 
 struct AerospaceWindowSwitcher {
     windows: Vec<WindowInfo>,
     search_query: String,
-    filtered_windows: Vec<usize>,
+    filtered_windows: Vec<MatchedWindow>,
     selected_index: Option<usize>,
+    /// The selection when a refresh started, restored once it lands.
+    reload_selection: Option<String>,
+    /// Set when the selection moves by keyboard so the list scrolls to it.
+    scroll_to_selected: bool,
+    group_by_app: bool,
+    /// List windows on every workspace, not just the ones on screen.
+    show_all: bool,
+    /// Match case exactly instead of following `case_mode`.
+    case_sensitive: bool,
+    /// Built for `case_mode` and `case_sensitive`, rebuilt when they change.
+    matcher: SkimMatcherV2,
+    /// One row per app, standing for its most recently used window.
+    compact: bool,
+    /// Select the whole query on the next frame so typing replaces it.
+    select_query: bool,
+    /// When the query last changed, while a debounced re-filter is pending.
+    filter_pending_since: Option<std::time::Instant>,
+    /// Highlighted entry of the Ctrl+Space action menu, while it's open.
+    action_menu: Option<usize>,
+    /// A short confirmation shown in place of the counts, and when it began.
+    flash: Option<(String, std::time::Instant)>,
+    /// Why the last fetch failed, shown in place of the list.
+    error: Option<String>,
+    /// The query is a `/regex` that failed to compile.
+    invalid_query: bool,
     is_loading: bool,
+    /// A fetch is running, possibly behind cached windows already shown.
+    fetching: bool,
     load_start_time: std::time::Instant,
-    window_to_focus: Option<String>,
-    windows_shared: Arc<Mutex<Option<Vec<WindowInfo>>>>,
+    /// Run by `main` once the switcher window is gone.
+    pending_action: Arc<Mutex<Option<Action>>>,
+    windows_shared: Arc<Mutex<Option<FetchResult>>>,
+    /// The workspace the user is on, for pulling windows to it.
+    focused_workspace: Option<String>,
+    /// The workspace the switcher was opened on before this one, for
+    /// stashing a window back there with Ctrl+B.
+    previous_workspace: Option<String>,
+    /// The window focused before the switcher opened, refocused on cancel.
+    previous_window: Option<String>,
+    /// The window aerospace has focused, marked with a dot in the list.
+    focused_window: Option<String>,
+    multiple_monitors: bool,
+    was_focused: bool,
+    /// `--auto-select` is waiting on the fresh list while the window is
+    /// hidden; cached windows don't count.
+    auto_select: bool,
+    /// The viewport starts invisible; `update` shows it, after the list is in
+    /// for `--auto-select` and `defer_show`.
+    hidden: bool,
+    /// Keyed by app name; `None` while loading or when the app has no icon.
+    icons: HashMap<String, Option<egui::TextureHandle>>,
+    icons_shared: Arc<Mutex<Vec<icons::LoadedIcon>>>,
+    pending_icons: usize,
+    /// Window ids focused through the switcher, most recent first.
+    mru: Vec<String>,
+    frecency: Frecency,
+    /// Set with `cycle_on_relaunch`; relaunches move the selection down.
+    cycle: Option<cycle::Listener>,
+    /// The list read once from stdin with `--stdin`, handed out again on
+    /// every refresh in place of asking aerospace.
+    stdin_windows: Option<Vec<WindowInfo>>,
+    /// Icons in a grid instead of the list, toggled with Ctrl+I.
+    grid: bool,
+    /// Icons per grid row as last laid out, for Up and Down.
+    grid_columns: usize,
+    /// Ids of windows marked with Shift+Space for a batch focus or move.
+    marked: HashSet<String>,
+    /// Apps pinned at runtime, on top of `config.pinned`.
+    pinned: Vec<String>,
+    /// Queries that led to a choice, most recent first.
+    history: Vec<String>,
+    /// Which `history` entry Up recalled into the query, until it's edited.
+    history_index: Option<usize>,
+    /// The height last requested by `fit_height`.
+    fitted_height: Option<f32>,
+    /// Last seen outer position of the viewport, saved on exit.
+    position: Option<egui::Pos2>,
+    accent_color: egui::Color32,
+    load_timeout: std::time::Duration,
+    config: Config,
+    args: Args,
 }
 
-impl Default for AerospaceWindowSwitcher {
-    fn default() -> Self {
-        let windows_shared = Arc::new(Mutex::new(None));
-        let windows_shared_clone = windows_shared.clone();
-
-        std::thread::spawn(move || {
-            let fetched = Self::fetch_windows();
-            let mut guard = windows_shared_clone.lock().unwrap();
-            *guard = Some(fetched);
-        });
-
-        Self {
+impl AerospaceWindowSwitcher {
+    fn new(
+        config: Config,
+        args: Args,
+        stdin_windows: Option<Vec<WindowInfo>>,
+        pending_action: Arc<Mutex<Option<Action>>>,
+    ) -> Self {
+        let mut switcher = Self {
             windows: Vec::new(),
             search_query: String::new(),
             filtered_windows: Vec::new(),
             selected_index: None,
+            reload_selection: None,
+            scroll_to_selected: false,
+            group_by_app: false,
+            show_all: true,
+            case_sensitive: matches!(config.case_mode, CaseMode::Respect),
+            matcher: SkimMatcherV2::default(),
+            compact: config.compact,
+            select_query: false,
+            filter_pending_since: None,
+            action_menu: None,
+            flash: None,
+            error: None,
+            invalid_query: false,
             is_loading: true,
+            fetching: false,
             load_start_time: std::time::Instant::now(),
-            window_to_focus: None,
-            windows_shared,
+            pending_action,
+            windows_shared: Arc::new(Mutex::new(None)),
+            focused_workspace: None,
+            previous_workspace: None,
+            previous_window: None,
+            focused_window: None,
+            was_focused: false,
+            auto_select: args.auto_select,
+            hidden: true,
+            multiple_monitors: false,
+            icons: HashMap::new(),
+            icons_shared: Arc::new(Mutex::new(Vec::new())),
+            pending_icons: 0,
+            mru: cache::load(MRU_CACHE).unwrap_or_default(),
+            frecency: Frecency::load(),
+            cycle: config.cycle_on_relaunch.then(cycle::Listener::default),
+            stdin_windows,
+            grid: config.grid,
+            grid_columns: 1,
+            marked: HashSet::new(),
+            pinned: cache::load(PINNED_CACHE).unwrap_or_default(),
+            history: cache::load(HISTORY_CACHE).unwrap_or_default(),
+            history_index: None,
+            fitted_height: None,
+            position: None,
+            accent_color: config.accent_color(args.theme),
+            load_timeout: config.load_timeout(),
+            config,
+            args,
+        };
+
+        switcher.matcher = switcher.build_matcher();
+
+        if switcher.config.remember_query {
+            if let Some(query) = cache::load::<String>(QUERY_CACHE) {
+                switcher.select_query = !query.is_empty();
+                switcher.search_query = query;
+            }
         }
+        if let Some(query) = &switcher.args.query {
+            switcher.search_query = query.clone();
+            switcher.select_query = false;
+        }
+
+        if let Some(monitor) = switcher.args.monitor.clone() {
+            if let Err(e) = switcher.check_monitor(&monitor) {
+                eprintln!("{}", e);
+                switcher.flash = Some((e, std::time::Instant::now()));
+                switcher.args.monitor = None;
+            }
+        }
+
+        // Show the last known list right away; the fresh fetch replaces it.
+        // Scoped lists aren't cached since e.g. `focused` changes meaning.
+        if switcher.args.workspace.is_none()
+            && switcher.args.monitor.is_none()
+            && !switcher.args.stdin
+        {
+            if let Some(cached) = cache::load(WINDOWS_CACHE) {
+                switcher.set_windows(cached);
+            }
+        }
+
+        switcher.spawn_fetch();
+        switcher
     }
-}
 
-impl AerospaceWindowSwitcher {
-    fn fetch_windows() -> Vec<WindowInfo> {
-        let output = match Command::new("aerospace")
-            .args(["list-windows", "--all"])
+    /// Re-fetches the window list; the query is kept and re-applied once
+    /// the new list arrives, and the selected window stays selected even if
+    /// it was the top result and no longer is.
+    fn refresh(&mut self) {
+        self.reload_selection = self.selected_window().map(|window| window.id.clone());
+        self.is_loading = true;
+        self.load_start_time = std::time::Instant::now();
+        self.spawn_fetch();
+    }
+
+    fn spawn_fetch(&mut self) {
+        self.fetching = true;
+        let windows_shared = self.windows_shared.clone();
+        if let Some(windows) = &self.stdin_windows {
+            *windows_shared.lock().unwrap() = Some(FetchResult {
+                windows: Ok(windows.clone()),
+                focused_workspace: None,
+                focused_window: None,
+            });
+            return;
+        }
+        if let Err(e) = self.config.check_aerospace_path() {
+            eprintln!("{}", e);
+            *windows_shared.lock().unwrap() = Some(FetchResult {
+                windows: Err(e),
+                focused_workspace: None,
+                focused_window: None,
+            });
+            return;
+        }
+        let aerospace = self.config.aerospace_path.clone();
+        let workspace = self
+            .args
+            .workspace
+            .clone()
+            .or_else(|| (!self.show_all).then(|| "visible".to_string()));
+        let monitor = self.args.monitor.clone();
+
+        std::thread::spawn(move || {
+            let fetched = FetchResult {
+                windows: Self::fetch_windows_retrying(
+                    &aerospace,
+                    workspace.as_deref(),
+                    monitor.as_deref(),
+                ),
+                focused_workspace: Self::fetch_focused_workspace(&aerospace),
+                focused_window: Self::fetch_focused_window(&aerospace),
+            };
+            let mut guard = windows_shared.lock().unwrap();
+            *guard = Some(fetched);
+        });
+    }
+
+    fn set_windows(&mut self, windows: Vec<WindowInfo>) {
+        // `filtered_windows` indexes the old list, so resolve the selection
+        // before it's replaced.
+        let selected_id = self
+            .reload_selection
+            .take()
+            .or_else(|| self.moved_selection_id());
+        self.filtered_windows.clear();
+        self.windows = windows;
+        self.windows
+            .retain(|window| !self.config.is_excluded(&window.name));
+        self.windows.iter_mut().for_each(WindowInfo::prepare_search);
+        let windows = &self.windows;
+        self.marked
+            .retain(|id| windows.iter().any(|window| window.id == *id));
+        self.multiple_monitors = self
+            .windows
+            .iter()
+            .any(|w| w.monitor != self.windows[0].monitor);
+        self.is_loading = false;
+        self.filter_windows();
+        self.restore_selection(selected_id.as_deref());
+        self.spawn_icon_fetch();
+    }
+
+    fn spawn_icon_fetch(&mut self) {
+        let mut names = Vec::new();
+        for window in &self.windows {
+            if !self.icons.contains_key(&window.name) {
+                self.icons.insert(window.name.clone(), None);
+                names.push(window.name.clone());
+            }
+        }
+        if names.is_empty() {
+            return;
+        }
+
+        self.pending_icons += names.len();
+        let icons_shared = self.icons_shared.clone();
+        std::thread::spawn(move || {
+            for name in names {
+                let icon = icons::load(&name);
+                icons_shared.lock().unwrap().push((name, icon));
+            }
+        });
+    }
+
+    fn run_aerospace(aerospace: &str, args: &[&str]) -> Result<std::process::Output, String> {
+        verbose!("running {} {}", aerospace, args.join(" "));
+        Command::new(aerospace)
+            .args(args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .output()
-        {
-            Ok(output) => output,
-            Err(e) => {
+            .map_err(|e| {
                 eprintln!("Failed to execute aerospace command: {}", e);
-                return Vec::new();
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    format!("{} not found on PATH ({})", aerospace, e)
+                } else {
+                    format!("Failed to run aerospace: {}", e)
+                }
+            })
+    }
+
+    fn fetch_focused_workspace(aerospace: &str) -> Option<String> {
+        Self::fetch_line(aerospace, &["list-workspaces", "--focused"])
+    }
+
+    fn fetch_focused_window(aerospace: &str) -> Option<String> {
+        Self::fetch_line(
+            aerospace,
+            &["list-windows", "--focused", "--format", "%{window-id}"],
+        )
+    }
+
+    /// The trimmed output of a command that prints a single value.
+    fn fetch_line(aerospace: &str, args: &[&str]) -> Option<String> {
+        let output = Self::run_aerospace(aerospace, args).ok()?;
+        let line = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !line.is_empty()).then_some(line)
+    }
+
+    /// Aerospace can fail or list nothing mid-transition, e.g. when the
+    /// hotkey fires as workspaces switch, so try a few times before settling
+    /// on an error or an empty list. A workspace or monitor may really be
+    /// empty, so only an unscoped empty list is retried.
+    fn fetch_windows_retrying(
+        aerospace: &str,
+        workspace: Option<&str>,
+        monitor: Option<&str>,
+    ) -> Result<Vec<WindowInfo>, String> {
+        let mut attempt = 1;
+        loop {
+            let result = Self::fetch_windows(aerospace, workspace, monitor);
+            let unscoped = workspace.is_none() && monitor.is_none();
+            let failed = result
+                .as_ref()
+                .map_or(true, |windows| unscoped && windows.is_empty());
+            if !failed || attempt == FETCH_ATTEMPTS {
+                return result;
             }
-        };
+            verbose!(
+                "list-windows attempt {} failed or was empty, retrying",
+                attempt
+            );
+            std::thread::sleep(FETCH_RETRY_DELAY * attempt);
+            attempt += 1;
+        }
+    }
+
+    /// With neither `workspace` nor `monitor`, every window is listed.
+    fn fetch_windows(
+        aerospace: &str,
+        workspace: Option<&str>,
+        monitor: Option<&str>,
+    ) -> Result<Vec<WindowInfo>, String> {
+        let mut args = vec!["list-windows"];
+        if let Some(workspace) = workspace {
+            args.extend(["--workspace", workspace]);
+        }
+        if let Some(monitor) = monitor {
+            args.extend(["--monitor", monitor]);
+        }
+        if workspace.is_none() && monitor.is_none() {
+            args.push("--all");
+        }
+
+        let mut formatted_args = args.clone();
+        formatted_args.extend(["--format", LIST_WINDOWS_FORMAT]);
+        let mut output = Self::run_aerospace(aerospace, &formatted_args)?;
+
+        // Older aerospace versions don't know `--format`; their default output
+        // is a `|` separated `id | app | title` without the location.
+        let mut with_location = true;
+        if !output.status.success() {
+            output = Self::run_aerospace(aerospace, &args)?;
+            with_location = false;
+        }
 
         if !output.status.success() {
             let error = String::from_utf8_lossy(&output.stderr);
             eprintln!("Aerospace command failed: {}", error);
-            return Vec::new();
-        }
-
-        let reader = BufReader::new(output.stdout.as_slice());
-        reader
-            .lines()
-            .map_while(Result::ok)
-            .filter(|line| !line.trim().is_empty())
-            .filter_map(|line| {
-                let parts: Vec<&str> = line.splitn(3, '|').collect();
-                if parts.len() >= 3 {
-                    Some(WindowInfo {
-                        id: parts[0].trim().to_string(),
-                        name: parts[1].trim().to_string(),
-                        info: parts[2].trim().to_string(),
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect()
+            return Err(format!("aerospace list-windows failed: {}", error.trim()));
+        }
+
+        let windows = parse_windows(output_lines(&output.stdout), with_location);
+        verbose!("parsed {} windows", windows.len());
+        Ok(windows)
     }
 
     fn filter_windows(&mut self) {
-        if self.search_query.is_empty() {
-            self.filtered_windows = (0..self.windows.len()).collect();
-            self.selected_index = Some(0);
-            return;
+        let selected_id = self.moved_selection_id();
+        self.filter_pending_since = None;
+        self.invalid_query = false;
+        self.filtered_windows = match self.search_query.strip_prefix('/') {
+            Some(pattern) => match Regex::new(pattern) {
+                Ok(regex) => self.regex_windows(&regex),
+                Err(_) => {
+                    self.invalid_query = true;
+                    Vec::new()
+                }
+            },
+            None => {
+                let (field, query) = SearchField::parse(&self.search_query);
+                rank_windows(&self.windows, query, field, &self.ranking())
+            }
+        };
+        if self.compact {
+            self.collapse_filtered_windows();
+        } else if self.group_by_app {
+            self.group_filtered_windows();
         }
+        // Last, and stable, so pinned apps keep their own order and groups.
+        let mut filtered = std::mem::take(&mut self.filtered_windows);
+        filtered.sort_by_key(|m| !self.is_pinned(&self.windows[m.index].name));
+        self.filtered_windows = filtered;
+        if let Some(max) = self.config.max_results {
+            if self.config.limit_empty_query || !SearchField::parse(&self.search_query).1.is_empty()
+            {
+                self.filtered_windows.truncate(max);
+            }
+        }
+        self.restore_selection(selected_id.as_deref());
+    }
+
+    /// Moves the selection down, wrapping to the top.
+    fn select_next(&mut self) {
+        self.selected_index =
+            Some((self.selected_index.unwrap_or(0) + 1) % self.filtered_windows.len());
+        self.scroll_to_selected = true;
+    }
+
+    /// Moves the selection up, wrapping to the bottom.
+    fn select_previous(&mut self) {
+        self.selected_index = Some(match self.selected_index {
+            Some(0) => self.filtered_windows.len() - 1,
+            Some(index) => index - 1,
+            None => 0,
+        });
+        self.scroll_to_selected = true;
+    }
+
+    /// Moves the grid selection a row up or down, stopping at either end.
+    fn select_grid_row(&mut self, down: bool) {
+        let current = self.selected_index.unwrap_or(0);
+        let last = self.filtered_windows.len() - 1;
+        self.selected_index = Some(if down {
+            (current + self.grid_columns).min(last)
+        } else {
+            current.saturating_sub(self.grid_columns)
+        });
+        self.scroll_to_selected = true;
+    }
 
+    /// Ctrl+Shift+C's case-sensitive search wins over `case_mode`.
+    fn build_matcher(&self) -> SkimMatcherV2 {
         let matcher = SkimMatcherV2::default();
-        let mut scored_indices: Vec<(usize, i64)> = self.windows
+        if self.case_sensitive {
+            return matcher.respect_case();
+        }
+        match self.config.case_mode {
+            CaseMode::Ignore => matcher.ignore_case(),
+            CaseMode::Smart | CaseMode::Respect => matcher.smart_case(),
+        }
+    }
+
+    fn ranking(&self) -> Ranking<'_> {
+        Ranking {
+            config: &self.config,
+            mru: &self.mru,
+            frecency: &self.frecency,
+            matcher: &self.matcher,
+            case_sensitive: self.case_sensitive,
+        }
+    }
+
+    /// Pinned apps come from the config plus those pinned with Ctrl+T.
+    fn is_pinned(&self, app_name: &str) -> bool {
+        self.config
+            .pinned
+            .iter()
+            .chain(&self.pinned)
+            .any(|pinned| pinned.eq_ignore_ascii_case(app_name))
+    }
+
+    fn toggle_pinned(&mut self) {
+        let Some(name) = self.selected_window().map(|window| window.name.clone()) else {
+            return;
+        };
+        let message = if let Some(pos) = self.pinned.iter().position(|p| *p == name) {
+            self.pinned.remove(pos);
+            format!("Unpinned {}", name)
+        } else if self.is_pinned(&name) {
+            format!("{} is pinned in the config", name)
+        } else {
+            self.pinned.push(name.clone());
+            format!("Pinned {}", name)
+        };
+        cache::save(PINNED_CACHE, &self.pinned);
+        self.flash = Some((message, std::time::Instant::now()));
+        self.filter_windows();
+    }
+
+    /// The selected window's id if the user moved off the top result. An
+    /// untouched selection follows the best match instead.
+    fn moved_selection_id(&self) -> Option<String> {
+        if self.selected_index == Some(0) {
+            return None;
+        }
+        self.selected_window().map(|window| window.id.clone())
+    }
+
+    /// Re-selects the window with `id` if it's still listed, else the top.
+    fn restore_selection(&mut self, id: Option<&str>) {
+        let position = id.and_then(|id| {
+            self.filtered_windows
+                .iter()
+                .position(|m| self.windows[m.index].id == id)
+        });
+        self.selected_index = Some(position.unwrap_or(0));
+        self.scroll_to_selected = true;
+    }
+
+    /// Regex mode keeps aerospace's order; there's no meaningful score.
+    fn regex_windows(&self, regex: &Regex) -> Vec<MatchedWindow> {
+        self.windows
             .iter()
             .enumerate()
             .filter_map(|(idx, window)| {
-                let name_score = matcher.fuzzy_match(&window.name, &self.search_query);
-                let info_score = matcher.fuzzy_match(&window.info, &self.search_query);
-                match (name_score, info_score) {
-                    (Some(s1), Some(s2)) => Some((idx, s1.max(s2))),
-                    (Some(s), None) | (None, Some(s)) => Some((idx, s)),
-                    (None, None) => None,
+                let mut matched = MatchedWindow::unmatched(idx);
+                if let Some(m) = regex.find(&window.name) {
+                    matched.name_indices = char_indices_in(&window.name, m.range());
+                } else if let Some(m) = regex.find(&window.info) {
+                    matched.info_indices = char_indices_in(&window.info, m.range());
+                } else {
+                    return None;
                 }
+                Some(matched)
             })
+            .collect()
+    }
+
+    /// Keeps one row per app at its best match's position. The row stands for
+    /// the app's most recently used matching window, but keeps the best
+    /// match's name highlights since all its windows share the name.
+    fn collapse_filtered_windows(&mut self) {
+        let mru_rank: HashMap<&str, usize> = self
+            .mru
+            .iter()
+            .enumerate()
+            .map(|(rank, id)| (id.as_str(), rank))
             .collect();
+        let recency = |idx: usize| {
+            mru_rank
+                .get(self.windows[idx].id.as_str())
+                .copied()
+                .unwrap_or(usize::MAX)
+        };
+
+        let mut rows: Vec<MatchedWindow> = Vec::new();
+        let mut row_of_app: HashMap<&str, usize> = HashMap::new();
+        for matched in std::mem::take(&mut self.filtered_windows) {
+            let name = self.windows[matched.index].name.as_str();
+            match row_of_app.get(name) {
+                Some(&row) => {
+                    if recency(matched.index) < recency(rows[row].index) {
+                        rows[row].index = matched.index;
+                    }
+                }
+                None => {
+                    row_of_app.insert(name, rows.len());
+                    rows.push(MatchedWindow {
+                        info_indices: Vec::new(),
+                        ..matched
+                    });
+                }
+            }
+        }
+        self.filtered_windows = rows;
+    }
+
+    /// Makes each app's windows contiguous. Apps are listed alphabetically
+    /// when browsing, or by their best match when searching; the stable sort
+    /// keeps the fuzzy ranking within a group.
+    fn group_filtered_windows(&mut self) {
+        let windows = &self.windows;
+        if SearchField::parse(&self.search_query).1.is_empty() {
+            self.filtered_windows
+                .sort_by_cached_key(|m| windows[m.index].name.to_lowercase());
+        } else {
+            let mut group_rank: HashMap<&str, usize> = HashMap::new();
+            for (rank, matched) in self.filtered_windows.iter().enumerate() {
+                group_rank
+                    .entry(windows[matched.index].name.as_str())
+                    .or_insert(rank);
+            }
+            self.filtered_windows
+                .sort_by_key(|m| group_rank[windows[m.index].name.as_str()]);
+        }
+    }
+
+    /// Shrinks the window to `height` so few results don't leave a tall
+    /// transparent area. The viewport isn't user-resizable, but programmatic
+    /// resizes still apply.
+    fn fit_height(&mut self, ctx: &egui::Context, height: f32) {
+        let height = height.min(self.config.window_height).round();
+        if self.fitted_height != Some(height) {
+            self.fitted_height = Some(height);
+            ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(egui::vec2(
+                self.config.window_width,
+                height,
+            )));
+        }
+    }
+
+    /// Snaps the list to whole rows so the last visible one isn't cut off.
+    fn list_height(&self, ui: &egui::Ui) -> f32 {
+        let row_height = self.config.item_height + ui.spacing().item_spacing.y;
+        let max_height = self.config.max_list_height.min(ui.available_height());
+        ((max_height / row_height).floor() * row_height).max(row_height)
+    }
+
+    /// How many rows PageUp/PageDown move by: one full list's worth.
+    fn page_rows(&self, ctx: &egui::Context) -> usize {
+        let row_height = self.config.item_height + ctx.style().spacing.item_spacing.y;
+        ((self.config.max_list_height / row_height).floor() as usize).max(1)
+    }
+
+    fn selected_window(&self) -> Option<&WindowInfo> {
+        let matched = self.filtered_windows.get(self.selected_index?)?;
+        Some(&self.windows[matched.index])
+    }
+
+    /// The checkmark of marked rows and the dot of the focused window.
+    fn row_prefix(&self, window: &WindowInfo) -> String {
+        let mut prefix = String::new();
+        if self.marked.contains(&window.id) {
+            prefix.push_str("✔ ");
+        }
+        if self.focused_window.as_ref() == Some(&window.id) {
+            prefix.push_str("● ");
+        }
+        prefix
+    }
+
+    /// With `align_names`, the chars every row's prefix and name are padded
+    /// or cut to: the longest listed, capped at `name_column_width`.
+    fn name_column_width(&self) -> Option<usize> {
+        if !self.config.align_names || self.compact {
+            return None;
+        }
+        let longest = self
+            .filtered_windows
+            .iter()
+            .map(|matched| {
+                let window = &self.windows[matched.index];
+                self.row_prefix(window).chars().count() + window.name.chars().count()
+            })
+            .max()
+            .unwrap_or(0);
+        Some(
+            self.config
+                .name_column_width
+                .map_or(longest, |width| longest.min(width)),
+        )
+    }
+
+    /// The grid view: app icons in wrapped rows, like the Cmd-Tab bar, with
+    /// the selected window's title below. Returns the height it took.
+    fn show_grid(&mut self, ctx: &egui::Context, ui: &mut egui::Ui) -> f32 {
+        let top = ui.cursor().top();
+        let spacing = ui.spacing().item_spacing;
+        self.grid_columns =
+            (((ui.available_width() + spacing.x) / (GRID_CELL_SIZE + spacing.x)) as usize).max(1);
+        let title_height = ui.text_style_height(&egui::TextStyle::Monospace) + spacing.y;
+        let max_height = self.config.max_list_height.min(ui.available_height()) - title_height;
+
+        let mut selected = self.selected_index.unwrap_or(0);
+        let mut window_to_focus = None;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, true])
+            .max_height(max_height.max(GRID_CELL_SIZE))
+            .show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for (idx, matched) in self.filtered_windows.iter().enumerate() {
+                        let window = &self.windows[matched.index];
+                        let (rect, response) = ui.allocate_exact_size(
+                            egui::vec2(GRID_CELL_SIZE, GRID_CELL_SIZE),
+                            egui::Sense::click(),
+                        );
+                        let visuals = ui.visuals();
+                        if idx == selected {
+                            ui.painter().rect_filled(rect, 6.0, self.accent_color);
+                            if self.scroll_to_selected {
+                                response.scroll_to_me(None);
+                            }
+                        }
+                        if self.marked.contains(&window.id) {
+                            ui.painter().rect_stroke(
+                                rect.shrink(1.0),
+                                6.0,
+                                visuals.selection.stroke,
+                                egui::StrokeKind::Inside,
+                            );
+                        }
 
-        scored_indices.sort_by(|a, b| b.1.cmp(&a.1));
-        self.filtered_windows = scored_indices.into_iter().map(|(idx, _)| idx).collect();
-        self.selected_index = Some(0);
+                        let icon_rect = egui::Rect::from_center_size(
+                            rect.center(),
+                            egui::vec2(GRID_ICON_SIZE, GRID_ICON_SIZE),
+                        );
+                        match self.icons.get(&window.name) {
+                            Some(Some(texture)) => {
+                                egui::Image::from_texture(texture).paint_at(ui, icon_rect);
+                            }
+                            // No icon: the app's initial on a plain tile.
+                            _ => {
+                                ui.painter()
+                                    .rect_filled(icon_rect, 8.0, visuals.faint_bg_color);
+                                ui.painter().text(
+                                    icon_rect.center(),
+                                    egui::Align2::CENTER_CENTER,
+                                    window.name.chars().next().unwrap_or('?'),
+                                    egui::FontId::proportional(GRID_ICON_SIZE / 2.0),
+                                    visuals.text_color(),
+                                );
+                            }
+                        }
+
+                        let response =
+                            response.on_hover_text(format!("{} | {}", window.name, window.info));
+                        let click_to_focus = self.config.click_to_focus;
+                        if response.double_clicked() || (response.clicked() && click_to_focus) {
+                            selected = idx;
+                            window_to_focus = Some(matched.index);
+                        } else if response.clicked() {
+                            selected = idx;
+                        }
+                    }
+                });
+            });
+        self.selected_index = Some(selected);
+        self.scroll_to_selected = false;
+
+        if let Some(window) = self.selected_window() {
+            let title = format!("{} | {}", window.name, window.info);
+            ui.add(egui::Label::new(egui::RichText::new(title).monospace()).truncate());
+        }
+
+        if let Some(idx) = window_to_focus {
+            self.selected_index = self.filtered_windows.iter().position(|m| m.index == idx);
+            if self.focus_selected_window() {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+        ui.min_rect().bottom() - top
+    }
+
+    /// The dim right-aligned label for a row, e.g. `2` or `2 @1` when the
+    /// windows are spread over several monitors.
+    fn location_tag(&self, window: &WindowInfo) -> Option<String> {
+        if window.workspace.is_empty() {
+            return None;
+        }
+        if self.multiple_monitors && !window.monitor.is_empty() {
+            Some(format!("{} @{}", window.workspace, window.monitor))
+        } else {
+            Some(window.workspace.clone())
+        }
     }
 
     fn is_loading_timed_out(&self) -> bool {
-        self.load_start_time.elapsed() > std::time::Duration::from_secs(2)
+        self.load_start_time.elapsed() > self.load_timeout
+    }
+
+    fn focus_selected_window(&mut self) -> bool {
+        match self.config.on_select.clone() {
+            Some(template) => self.queue_action(|window| Action::Run {
+                window_id: window.id.clone(),
+                command: expand_template(&template, window),
+            }),
+            None => self.queue_action(|window| Action::Focus {
+                window_id: window.id.clone(),
+            }),
+        }
+    }
+
+    /// Falls back to a plain focus if the current workspace is unknown.
+    fn move_selected_window_here(&mut self) -> bool {
+        let Some(workspace) = self.focused_workspace.clone() else {
+            return self.focus_selected_window();
+        };
+        self.queue_action(|window| Action::MoveHere {
+            window_id: window.id.clone(),
+            workspace: workspace.clone(),
+        })
+    }
+
+    /// Moves the selected window to the lowest numbered workspace aerospace
+    /// doesn't list yet, and focuses it there.
+    fn move_selected_window_to_new_workspace(&mut self) -> bool {
+        let args = ["list-workspaces", "--all"];
+        let workspaces = match Self::run_aerospace(&self.config.aerospace_path, &args) {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .collect::<HashSet<_>>(),
+            Ok(output) => {
+                let error = String::from_utf8_lossy(&output.stderr).trim().to_string();
+                self.flash = Some((error, std::time::Instant::now()));
+                return false;
+            }
+            Err(e) => {
+                self.flash = Some((e, std::time::Instant::now()));
+                return false;
+            }
+        };
+        let workspace = (1..)
+            .map(|number: u32| number.to_string())
+            .find(|name| !workspaces.contains(name))
+            .unwrap_or_default();
+        self.queue_action(|window| Action::MoveHere {
+            window_id: window.id.clone(),
+            workspace: workspace.clone(),
+        })
+    }
+
+    fn send_selected_window_back(&mut self) -> bool {
+        let Some(workspace) = self.previous_workspace.clone() else {
+            self.flash = Some((
+                "No previous workspace yet".to_string(),
+                std::time::Instant::now(),
+            ));
+            return false;
+        };
+        self.queue_action(|window| Action::MoveTo {
+            window_id: window.id.clone(),
+            workspace: workspace.clone(),
+        })
+    }
+
+    /// Hands `main` an action on the selected window, or on every marked
+    /// one, to run after closing. Keeps the switcher open with a message
+    /// instead if the selected window is gone.
+    fn queue_action(&mut self, action: impl Fn(&WindowInfo) -> Action) -> bool {
+        if !self.marked.is_empty() {
+            return self.queue_marked(action);
+        }
+        let Some(id) = self.selected_window().map(|window| window.id.clone()) else {
+            return false;
+        };
+        if !self.window_exists(&id) {
+            self.flash = Some((
+                format!("Window {} no longer exists", id),
+                std::time::Instant::now(),
+            ));
+            self.refresh();
+            return false;
+        }
+
+        if self.choose_selected_window().is_none() {
+            return false;
+        }
+        self.remember_back();
+        let Some(window) = self.selected_window() else {
+            return false;
+        };
+        *self.pending_action.lock().unwrap() = Some(action(window));
+        true
+    }
+
+    /// Marked windows in list order, including any the query hides, with
+    /// the selected one last so it's the one left focused.
+    fn queue_marked(&mut self, action: impl Fn(&WindowInfo) -> Action) -> bool {
+        let selected_id = self.selected_window().map(|window| window.id.clone());
+        let mut windows: Vec<&WindowInfo> = self
+            .windows
+            .iter()
+            .filter(|window| self.marked.contains(&window.id))
+            .collect();
+        windows.sort_by_key(|window| Some(&window.id) == selected_id.as_ref());
+        let ids: Vec<String> = windows.iter().map(|window| window.id.clone()).collect();
+        let actions = windows.into_iter().map(action).collect();
+        for id in &ids {
+            self.record_choice(id);
+        }
+        self.remember_back();
+        *self.pending_action.lock().unwrap() = Some(Action::Batch(actions));
+        true
+    }
+
+    /// Lets `--back` return to the window focused before this switch.
+    fn remember_back(&self) {
+        if let Some(id) = self.previous_window.as_ref().filter(|_| !self.args.print) {
+            cache::save(BACK_CACHE, id);
+        }
+    }
+
+    fn toggle_marked(&mut self) {
+        let Some(id) = self.selected_window().map(|window| window.id.clone()) else {
+            return;
+        };
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+    }
+
+    /// Accepts aerospace's own `focused`, `mouse` and `all`, or an id from
+    /// `list-monitors`. Assumes it's fine if aerospace can't be asked.
+    fn check_monitor(&self, monitor: &str) -> Result<(), String> {
+        if matches!(monitor, "focused" | "mouse" | "all") {
+            return Ok(());
+        }
+        let args = ["list-monitors", "--format", "%{monitor-id}"];
+        match Self::run_aerospace(&self.config.aerospace_path, &args) {
+            Ok(output) if output.status.success() => {
+                let known = String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .any(|line| line.trim() == monitor);
+                if known {
+                    Ok(())
+                } else {
+                    Err(format!("No monitor {}, listing all monitors", monitor))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checked before closing since once the switcher is gone there's nowhere
+    /// to report a failed focus. Assumes the window exists if aerospace
+    /// can't be asked.
+    fn window_exists(&self, id: &str) -> bool {
+        if self.stdin_windows.is_some() {
+            return true;
+        }
+        let args = ["list-windows", "--all", "--format", "%{window-id}"];
+        match Self::run_aerospace(&self.config.aerospace_path, &args) {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line.trim() == id),
+            _ => true,
+        }
+    }
+
+    /// Toggles fullscreen right away so a failure, e.g. an aerospace too old
+    /// for `fullscreen --window-id`, can be shown; the focus still waits
+    /// for the switcher to close. Marked windows are toggled after closing.
+    fn fullscreen_selected_window(&mut self) -> bool {
+        if !self.marked.is_empty() {
+            return self.queue_action(|window| Action::ToggleFullscreen {
+                window_id: window.id.clone(),
+            });
+        }
+        let Some(id) = self.selected_window().map(|window| window.id.clone()) else {
+            return false;
+        };
+
+        let args = ["fullscreen", "--window-id", &id];
+        let error = match Self::run_aerospace(&self.config.aerospace_path, &args) {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => Some(format!(
+                "aerospace fullscreen failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => Some(e),
+        };
+        if let Some(error) = error {
+            eprintln!("{}", error);
+            self.flash = Some((error, std::time::Instant::now()));
+            return false;
+        }
+        self.focus_selected_window()
+    }
+
+    /// Focuses the selected window but stays open, taking keyboard focus
+    /// back so the next one can be peeked at. Not a choice, so the MRU
+    /// order is left alone.
+    fn peek_selected_window(&mut self, ctx: &egui::Context) {
+        let Some(window_id) = self.selected_window().map(|window| window.id.clone()) else {
+            return;
+        };
+        let focus = Action::Focus {
+            window_id: window_id.clone(),
+        };
+        if let Err(e) = focus.run(&self.config.aerospace_path) {
+            eprintln!("Failed to execute aerospace command: {}", e);
+            self.flash = Some((e.to_string(), std::time::Instant::now()));
+            return;
+        }
+        self.focused_window = Some(window_id);
+        // The focus change isn't the user leaving.
+        self.was_focused = false;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    }
+
+    /// Returns true if the switcher should close.
+    fn run_menu_entry(&mut self, entry: MenuEntry) -> bool {
+        match entry {
+            MenuEntry::Focus => self.focus_selected_window(),
+            MenuEntry::MoveHere => self.move_selected_window_here(),
+            MenuEntry::ToggleFullscreen => self.fullscreen_selected_window(),
+            MenuEntry::Close => {
+                self.close_selected_window();
+                false
+            }
+        }
+    }
+
+    /// Draws the Ctrl+Space menu and handles its keys. While it's open it
+    /// owns navigation, Enter and Escape.
+    fn show_action_menu(&mut self, ctx: &egui::Context, mut highlighted: usize) {
+        let mut chosen = None;
+        ctx.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::Escape) {
+                self.action_menu = None;
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Enter) {
+                chosen = Some(highlighted);
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown)
+                || i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+            {
+                highlighted = (highlighted + 1) % MenuEntry::ALL.len();
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp) {
+                highlighted = (highlighted + MenuEntry::ALL.len() - 1) % MenuEntry::ALL.len();
+            }
+        });
+        if self.action_menu.is_none() {
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("action_menu"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style())
+                    .fill(ui.visuals().extreme_bg_color)
+                    .show(ui, |ui| {
+                        for (idx, entry) in MenuEntry::ALL.iter().enumerate() {
+                            let label = ui.selectable_label(idx == highlighted, entry.label());
+                            if label.clicked() {
+                                chosen = Some(idx);
+                            }
+                        }
+                    });
+            });
+
+        self.action_menu = Some(highlighted);
+        if let Some(idx) = chosen {
+            self.action_menu = None;
+            if self.run_menu_entry(MenuEntry::ALL[idx]) {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+    }
+
+    /// The selected window's id, recorded as most recently used.
+    fn choose_selected_window(&mut self) -> Option<String> {
+        let id = self.selected_window()?.id.clone();
+        self.record_choice(&id);
+        Some(id)
+    }
+
+    fn record_choice(&mut self, id: &str) {
+        verbose!("chose window {}", id);
+        // A scripted pick isn't necessarily a focus.
+        if !self.args.print {
+            self.mru.retain(|mru_id| mru_id != id);
+            self.mru.insert(0, id.to_string());
+            self.mru.truncate(MRU_LEN);
+            cache::save(MRU_CACHE, &self.mru);
+            self.frecency.record(id);
+        }
+        let query = self.search_query.trim();
+        if !query.is_empty() {
+            let query = query.to_string();
+            self.history.retain(|entry| *entry != query);
+            self.history.insert(0, query);
+            self.history.truncate(HISTORY_LEN);
+            cache::save(HISTORY_CACHE, &self.history);
+        }
     }
 
-    fn focus_selected_window(&mut self) -> bool {
-        if let Some(selected) = self.selected_index {
-            if let Some(&idx) = self.filtered_windows.get(selected) {
-                self.window_to_focus = Some(self.windows[idx].id.clone());
-                return true;
-            }
+    fn clear_query(&mut self) {
+        self.search_query.clear();
+        self.history_index = None;
+        self.filter_windows();
+    }
+
+    /// Puts `history[index]` in the query, or clears it for `None`.
+    fn recall_query(&mut self, index: Option<usize>) {
+        self.history_index = index;
+        self.search_query = index
+            .map(|index| self.history[index].clone())
+            .unwrap_or_default();
+        self.select_query = true;
+        self.filter_windows();
+    }
+
+    /// Closes the selected window through aerospace and drops it from the
+    /// list, keeping the switcher open.
+    fn close_selected_window(&mut self) {
+        let Some(selected) = self.selected_index else {
+            return;
+        };
+        let Some(matched) = self.filtered_windows.get(selected) else {
+            return;
+        };
+
+        let win_idx = matched.index;
+        verbose!("closing window {}", self.windows[win_idx].id);
+        let args = ["close", "--window-id", &self.windows[win_idx].id];
+        let error = match Self::run_aerospace(&self.config.aerospace_path, &args) {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => Some(format!(
+                "aerospace close failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => Some(e),
+        };
+        // The window is still there, so keep its row.
+        if let Some(error) = error {
+            eprintln!("{}", error);
+            self.flash = Some((error, std::time::Instant::now()));
+            return;
+        }
+
+        let closed = self.windows.remove(win_idx);
+        self.marked.remove(&closed.id);
+        self.filtered_windows.remove(selected);
+        for matched in &mut self.filtered_windows {
+            if matched.index > win_idx {
+                matched.index -= 1;
+            }
+        }
+        self.selected_index = Some(selected.min(self.filtered_windows.len().saturating_sub(1)));
+    }
+}
+
+impl eframe::App for AerospaceWindowSwitcher {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if let Some(cycle) = &self.cycle {
+            cycle.stop();
+        }
+        if self.config.remember_query {
+            cache::save(QUERY_CACHE, &self.search_query);
+        }
+        if let Some(position) = self.position {
+            cache::save(POSITION_CACHE, &(position.x, position.y));
+        }
+        if self.args.workspace.is_none()
+            && self.args.monitor.is_none()
+            && !self.args.stdin
+            && self.show_all
+        {
+            cache::save(WINDOWS_CACHE, &self.windows);
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if let Some(rect) = ctx.input(|i| i.viewport().outer_rect) {
+            self.position = Some(rect.min);
+        }
+
+        // The window opens inactive, so only losing focus it once had counts.
+        match ctx.input(|i| i.viewport().focused) {
+            Some(true) => self.was_focused = true,
+            Some(false) if self.was_focused && self.config.close_on_blur => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                return;
+            }
+            _ => {}
+        }
+
+        let loaded_icons = std::mem::take(&mut *self.icons_shared.lock().unwrap());
+        self.pending_icons -= loaded_icons.len();
+        for (name, image) in loaded_icons {
+            let texture = image
+                .map(|image| ctx.load_texture(format!("icon-{}", name), image, Default::default()));
+            self.icons.insert(name, texture);
+        }
+        if self.pending_icons > 0 {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        if let Some(since) = self.filter_pending_since {
+            // Anything acting on the selection needs current results.
+            let acting = ctx.input(|i| {
+                i.modifiers.ctrl
+                    || i.modifiers.alt
+                    || self.config.keybindings.any_pressed(i)
+                    || [
+                        egui::Key::Enter,
+                        egui::Key::Home,
+                        egui::Key::End,
+                        egui::Key::PageUp,
+                        egui::Key::PageDown,
+                    ]
+                    .iter()
+                    .any(|&key| i.key_pressed(key))
+            });
+            let remaining = DEBOUNCE_DELAY.saturating_sub(since.elapsed());
+            if acting || remaining.is_zero() {
+                self.filter_windows();
+            } else {
+                ctx.request_repaint_after(remaining);
+            }
+        }
+
+        let fetched = self.windows_shared.lock().unwrap().take();
+        if let Some(fetched) = fetched {
+            self.fetching = false;
+            if self.focused_workspace.is_none() {
+                self.previous_workspace = fetched
+                    .focused_workspace
+                    .as_deref()
+                    .and_then(remember_workspace);
+            }
+            self.focused_workspace = fetched.focused_workspace;
+            // A refresh could see the switcher itself focused; keep the
+            // window from launch.
+            if self.previous_window.is_none() {
+                self.previous_window = fetched.focused_window.clone();
+            }
+            match fetched.windows {
+                Ok(windows) => {
+                    self.error = None;
+                    // Same here: the switcher isn't in the list, so only a
+                    // listed window replaces the last known one.
+                    if let Some(id) = fetched.focused_window {
+                        if windows.iter().any(|window| window.id == id) {
+                            self.focused_window = Some(id);
+                        }
+                    }
+                    self.set_windows(windows);
+                }
+                Err(error) => {
+                    self.error = Some(error);
+                    self.set_windows(Vec::new());
+                }
+            }
+            if std::mem::take(&mut self.auto_select)
+                && self.filtered_windows.len() == 1
+                && self.focus_selected_window()
+            {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                return;
+            }
+        } else if self.is_loading && self.is_loading_timed_out() {
+            self.is_loading = false;
+            self.auto_select = false;
+            self.filter_windows();
+        }
+        // `--auto-select` stays hidden until the fresh fetch, even if the
+        // cached list already ended loading.
+        if self.hidden && !self.auto_select && !(self.config.defer_show && self.is_loading) {
+            self.hidden = false;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+        }
+        // Nothing else wakes the event loop when the fetch lands, and this
+        // also keeps the loading spinner's clock ticking. Once it's in the
+        // app goes back to repainting only on input.
+        if self.fetching {
+            ctx.request_repaint_after(FETCH_POLL_INTERVAL);
+        }
+        if let Some(cycle) = &mut self.cycle {
+            let listening = cycle.poll();
+            // Held until there are rows, so early presses still count.
+            let presses = if self.filtered_windows.is_empty() {
+                0
+            } else {
+                cycle.take()
+            };
+            if listening {
+                ctx.request_repaint_after(FETCH_POLL_INTERVAL);
+            } else if cycle.pending() == 0 || !self.is_loading {
+                // Relaunches can no longer be presses, and any held ones
+                // have nothing left to wait for.
+                cycle.stop();
+                self.cycle = None;
+            }
+            for _ in 0..presses {
+                self.select_next();
+            }
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::Space))
+            && self.selected_window().is_some()
+        {
+            self.action_menu = Some(0);
+        }
+        if let Some(highlighted) = self.action_menu {
+            self.show_action_menu(ctx, highlighted);
+        }
+
+        // Consumed before the search box sees it, where e.g. Ctrl+W deletes
+        // a word.
+        let key_press = ctx.input_mut(|i| self.config.keybindings.consume(i));
+        let key_action = key_press.as_ref().map(|(action, _)| *action);
+
+        let clear = ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::U));
+        let clear_on_escape = key_action == Some(KeyAction::Cancel)
+            && self.config.escape_clears_query
+            && !self.search_query.is_empty();
+        if clear || clear_on_escape {
+            self.clear_query();
+        } else if key_action == Some(KeyAction::Cancel) {
+            // Hand activation back rather than leaving it wherever macOS
+            // puts it. With `--print` a cancel must print nothing.
+            if let Some(window_id) = self.previous_window.clone().filter(|_| !self.args.print) {
+                *self.pending_action.lock().unwrap() = Some(Action::Focus { window_id });
+            }
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return;
+        }
+
+        if key_action == Some(KeyAction::Close) {
+            self.close_selected_window();
+        }
+
+        if ctx.input_mut(|i| {
+            i.consume_key(egui::Modifiers::NONE, egui::Key::F5)
+                || i.consume_key(egui::Modifiers::CTRL, egui::Key::R)
+        }) {
+            self.refresh();
+        }
+
+        // Before Ctrl+C, which would also match with Shift held.
+        if ctx.input_mut(|i| {
+            i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::C)
+        }) {
+            self.case_sensitive = !self.case_sensitive;
+            self.matcher = self.build_matcher();
+            let state = if self.case_sensitive { "on" } else { "off" };
+            self.flash = Some((
+                format!("Case-sensitive search {}", state),
+                std::time::Instant::now(),
+            ));
+            self.filter_windows();
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::C)) {
+            if let Some(window) = self.selected_window() {
+                ctx.copy_text(window.info.clone());
+                self.flash = Some(("Copied title".to_string(), std::time::Instant::now()));
+            }
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::G)) {
+            self.group_by_app = !self.group_by_app;
+            self.filter_windows();
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::D)) {
+            self.compact = !self.compact;
+            self.filter_windows();
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::I)) {
+            self.grid = !self.grid;
+            self.scroll_to_selected = true;
+        }
+
+        // Shift+Space would otherwise also type a space into the query.
+        let mark = ctx.input_mut(|i| {
+            let pressed = i.consume_key(egui::Modifiers::SHIFT, egui::Key::Space);
+            if pressed {
+                i.events
+                    .retain(|event| !matches!(event, egui::Event::Text(text) if text == " "));
+            }
+            pressed
+        });
+        if mark {
+            self.toggle_marked();
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::T)) {
+            self.toggle_pinned();
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::H)) {
+            self.show_all = !self.show_all;
+            let shown = if self.show_all { "all" } else { "visible" };
+            self.flash = Some((
+                format!("Showing {} windows", shown),
+                std::time::Instant::now(),
+            ));
+            self.refresh();
+        }
+
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::ALT, egui::Key::Enter))
+            && self.move_selected_window_here()
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return;
         }
-        false
-    }
-}
 
-impl eframe::App for AerospaceWindowSwitcher {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if self.is_loading {
-            let should_update = {
-                let mut guard = self.windows_shared.lock().unwrap();
-                if let Some(fetched) = guard.take() {
-                    self.windows = fetched;
-                    true
-                } else if self.is_loading_timed_out() {
-                    true
-                } else {
-                    false
-                }
-            };
-            if should_update {
-                self.is_loading = false;
-                self.filter_windows();
-            }
+        if ctx.input_mut(|i| {
+            i.consume_key(egui::Modifiers::CTRL | egui::Modifiers::SHIFT, egui::Key::N)
+        }) && self.move_selected_window_to_new_workspace()
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return;
         }
 
-        if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::B))
+            && self.send_selected_window_back()
+        {
             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             return;
         }
 
-        if ctx.input(|i| i.key_pressed(egui::Key::Enter))
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::SHIFT, egui::Key::Enter)) {
+            self.peek_selected_window(ctx);
+        }
+
+        if key_action == Some(KeyAction::Select)
             && self.selected_index.is_some()
             && self.focus_selected_window()
         {
-            if let Some(window_id) = self.window_to_focus.take() {
-                let _ = Command::new("sh")
-                    .args(["-c", &format!("sleep 0.05 && aerospace focus --window-id {}", window_id)])
-                    .spawn();
-            }
             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             return;
         }
 
-        if !self.filtered_windows.is_empty() {
-            if ctx.input(|i| {
-                i.key_pressed(egui::Key::ArrowDown)
-                    || (i.modifiers.ctrl
-                        && (i.key_pressed(egui::Key::N) || i.key_pressed(egui::Key::J)))
-            }) {
-                self.selected_index =
-                    Some((self.selected_index.unwrap_or(0) + 1) % self.filtered_windows.len());
-            } else if ctx.input(|i| {
-                i.key_pressed(egui::Key::ArrowUp)
-                    || (i.modifiers.ctrl
-                        && (i.key_pressed(egui::Key::P) || i.key_pressed(egui::Key::K)))
-            }) {
-                self.selected_index = Some(if let Some(index) = self.selected_index {
-                    if index == 0 {
-                        self.filtered_windows.len() - 1
-                    } else {
-                        index - 1
-                    }
-                } else {
-                    0
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, egui::Key::F))
+            && self.fullscreen_selected_window()
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            return;
+        }
+
+        // Ctrl+N picks the Nth row from the top of the list, whether or not
+        // it's scrolled into view.
+        const QUICK_SELECT_KEYS: [egui::Key; 9] = [
+            egui::Key::Num1,
+            egui::Key::Num2,
+            egui::Key::Num3,
+            egui::Key::Num4,
+            egui::Key::Num5,
+            egui::Key::Num6,
+            egui::Key::Num7,
+            egui::Key::Num8,
+            egui::Key::Num9,
+        ];
+        let quick_select = QUICK_SELECT_KEYS
+            .iter()
+            .position(|&key| ctx.input_mut(|i| i.consume_key(egui::Modifiers::CTRL, key)));
+        if let Some(row) = quick_select {
+            if row < self.filtered_windows.len() {
+                self.selected_index = Some(row);
+                if self.focus_selected_window() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    return;
+                }
+            }
+        }
+
+        let recall = recalls_history(
+            key_press.as_ref(),
+            &self.search_query,
+            self.selected_index,
+            self.history_index,
+        );
+        if recall && !self.history.is_empty() {
+            let index = self
+                .history_index
+                .map_or(0, |index| (index + 1).min(self.history.len() - 1));
+            self.recall_query(Some(index));
+        } else if !self.filtered_windows.is_empty() {
+            if matches!(key_action, Some(KeyAction::Up | KeyAction::Down)) {
+                // Moving through the results ends the recall.
+                self.history_index = None;
+            }
+            if self.grid {
+                // Only the grid takes Left and Right from the query's cursor.
+                let (left, right) = ctx.input_mut(|i| {
+                    (
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowLeft),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowRight),
+                    )
                 });
+                if key_action == Some(KeyAction::Down) {
+                    self.select_grid_row(true);
+                } else if key_action == Some(KeyAction::Up) {
+                    self.select_grid_row(false);
+                } else if right {
+                    self.select_next();
+                } else if left {
+                    self.select_previous();
+                }
+            } else if key_action == Some(KeyAction::Down) {
+                self.select_next();
+            } else if key_action == Some(KeyAction::Up) {
+                self.select_previous();
+            }
+
+            // Home and End would otherwise move the cursor in the query.
+            let last = self.filtered_windows.len() - 1;
+            let page = self.page_rows(ctx);
+            let jump = ctx.input_mut(|i| {
+                let current = self.selected_index.unwrap_or(0);
+                if i.consume_key(egui::Modifiers::NONE, egui::Key::Home) {
+                    Some(0)
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::End) {
+                    Some(last)
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::PageUp) {
+                    Some(current.saturating_sub(page))
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::PageDown) {
+                    Some((current + page).min(last))
+                } else {
+                    None
+                }
+            });
+            if let Some(index) = jump {
+                self.selected_index = Some(index);
+                self.scroll_to_selected = true;
+            }
+        }
+
+        if self.config.show_preview && !self.is_loading {
+            if let Some(window) = self.selected_window() {
+                egui::TopBottomPanel::bottom("preview")
+                    .exact_height(PREVIEW_HEIGHT)
+                    .show(ctx, |ui| {
+                        egui::ScrollArea::vertical()
+                            .id_salt("preview")
+                            .show(ui, |ui| show_preview(ui, window));
+                    });
             }
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.add_space(PADDING_TOP);
 
+            let search_id = egui::Id::new("search_query");
+            if std::mem::take(&mut self.select_query) {
+                let mut state =
+                    egui::text_edit::TextEditState::load(ctx, search_id).unwrap_or_default();
+                state
+                    .cursor
+                    .set_char_range(Some(egui::text::CCursorRange::two(
+                        egui::text::CCursor::new(0),
+                        egui::text::CCursor::new(self.search_query.chars().count()),
+                    )));
+                state.store(ctx, search_id);
+            }
+
+            let mut search_box = egui::TextEdit::singleline(&mut self.search_query)
+                .id(search_id)
+                .frame(true)
+                .margin(egui::vec2(8.0, 8.0))
+                .font(egui::TextStyle::Monospace)
+                .hint_text(
+                    egui::RichText::new("Type to filter windows… · Enter to focus · Esc to cancel")
+                        .color(ui.visuals().weak_text_color()),
+                )
+                // Keeps Tab from moving focus out; a single line never indents.
+                .lock_focus(true);
+            let no_matches =
+                !self.is_loading && !self.windows.is_empty() && self.filtered_windows.is_empty();
+            if self.invalid_query {
+                search_box = search_box
+                    .background_color(egui::Color32::from_rgba_premultiplied(120, 30, 30, 180));
+            } else if no_matches {
+                search_box = search_box
+                    .background_color(egui::Color32::from_rgba_premultiplied(90, 70, 20, 120));
+            }
             let search_response = ui.add_sized(
-                [ui.available_width(), SEARCH_BOX_HEIGHT],
-                egui::TextEdit::singleline(&mut self.search_query)
-                    .frame(true)
-                    .margin(egui::vec2(8.0, 8.0))
-                    .font(egui::TextStyle::Monospace),
+                [ui.available_width(), self.config.search_box_height],
+                search_box,
             );
 
             if search_response.changed() {
-                self.filter_windows();
+                self.history_index = None;
+                if self.windows.len() > DEBOUNCE_MIN_WINDOWS {
+                    self.filter_pending_since = Some(std::time::Instant::now());
+                    ctx.request_repaint_after(DEBOUNCE_DELAY);
+                } else {
+                    self.filter_windows();
+                }
             }
 
             if !ui.memory(|m| m.has_focus(search_response.id)) {
@@ -218,68 +1806,802 @@ impl eframe::App for AerospaceWindowSwitcher {
 
             ui.add_space(8.0);
 
+            // Always reserve the line so the list doesn't jump once loaded.
+            let (status_rect, _) = ui.allocate_exact_size(
+                egui::vec2(ui.available_width(), STATUS_LINE_HEIGHT),
+                egui::Sense::hover(),
+            );
+            self.flash
+                .take_if(|(_, since)| since.elapsed() >= FLASH_DURATION);
+            let status = match &self.flash {
+                Some((message, since)) => {
+                    ctx.request_repaint_after(FLASH_DURATION.saturating_sub(since.elapsed()));
+                    Some(message.clone())
+                }
+                None => (!self.is_loading).then(|| {
+                    let mut counts = format!(
+                        "{} / {} windows",
+                        self.filtered_windows.len(),
+                        self.windows.len()
+                    );
+                    if !self.marked.is_empty() {
+                        counts.push_str(&format!(" · {} marked", self.marked.len()));
+                    }
+                    counts
+                }),
+            };
+            if let Some(status) = status {
+                ui.painter().text(
+                    status_rect.left_center(),
+                    egui::Align2::LEFT_CENTER,
+                    status,
+                    egui::TextStyle::Small.resolve(ui.style()),
+                    ui.visuals().weak_text_color(),
+                );
+            }
+
             if self.is_loading {
+                ui.vertical_centered(|ui| {
+                    ui.add_space(PADDING_TOP);
+                    ui.add(egui::Spinner::new());
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Loading windows... {:.1}s",
+                            self.load_start_time.elapsed().as_secs_f32()
+                        ))
+                        .color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                });
+            } else if let Some(error) = &self.error {
+                ui.centered_and_justified(|ui| {
+                    ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+                });
+            } else if self.windows.is_empty() {
                 ui.centered_and_justified(|ui| {
                     ui.label(
-                        egui::RichText::new("Loading windows...")
+                        egui::RichText::new("No windows found — is aerospace running?")
                             .color(egui::Color32::from_rgb(180, 180, 180)),
                     );
                 });
+            } else if self.filtered_windows.is_empty() {
+                let message = if self.invalid_query {
+                    "Invalid regular expression"
+                } else {
+                    "No matching windows"
+                };
+                ui.centered_and_justified(|ui| {
+                    ui.label(
+                        egui::RichText::new(message).color(egui::Color32::from_rgb(180, 180, 180)),
+                    );
+                });
+            } else if self.grid {
+                let list_top = ui.cursor().top();
+                let grid_height = self.show_grid(ctx, ui);
+                let below_list = ctx.screen_rect().bottom() - ui.max_rect().bottom();
+                self.fit_height(ctx, list_top + grid_height + below_list);
             } else {
-                egui::ScrollArea::vertical()
+                let list_top = ui.cursor().top();
+                // A thin bar that's always drawn, rather than egui's default
+                // floating one that only shows on hover, so long lists show
+                // where the selection is.
+                ui.spacing_mut().scroll = egui::style::ScrollStyle::thin();
+                let list = egui::ScrollArea::vertical()
+                    .scroll_bar_visibility(
+                        egui::scroll_area::ScrollBarVisibility::VisibleWhenNeeded,
+                    )
                     .auto_shrink([false, true])
-                    .max_height(MAX_LIST_HEIGHT)
+                    .max_height(self.list_height(ui))
                     .show(ui, |ui| {
                         let mut selected = self.selected_index.unwrap_or(0);
                         let mut window_to_focus = None;
+                        // A resting cursor must not fight keyboard navigation,
+                        // so hovering only selects while the pointer moves.
+                        let pointer_moved = ctx.input(|i| i.pointer.delta() != egui::Vec2::ZERO);
+                        let name_width = self.name_column_width();
 
-                        for (idx, &win_idx) in self.filtered_windows.iter().enumerate() {
+                        for (idx, matched) in self.filtered_windows.iter().enumerate() {
+                            let win_idx = matched.index;
                             let window = &self.windows[win_idx];
                             let is_selected = selected == idx;
+                            let prefix = self.row_prefix(window);
+
+                            // Headers are plain labels, so keyboard navigation
+                            // over `filtered_windows` never lands on them.
+                            let starts_group = idx == 0
+                                || self.windows[self.filtered_windows[idx - 1].index].name
+                                    != window.name;
+                            if self.group_by_app && !self.compact && starts_group {
+                                ui.label(
+                                    egui::RichText::new(&window.name)
+                                        .small()
+                                        .color(ui.visuals().weak_text_color()),
+                                );
+                            }
+
+                            let bar = matches!(self.config.selection_style, SelectionStyle::Bar);
+                            let highlight = if is_selected && !bar {
+                                self.args.theme.selected_highlight()
+                            } else {
+                                self.accent_color.to_opaque()
+                            };
+                            let name_color = self
+                                .config
+                                .colorize_apps
+                                .then(|| app_color(&window.name, ui.visuals().dark_mode));
+                            let label = RowLabel {
+                                highlight,
+                                compact: self.compact,
+                                prefix: &prefix,
+                                name_width,
+                                name_color,
+                            };
+                            let icon = match self.icons.get(&window.name) {
+                                Some(Some(texture)) => Some(texture),
+                                _ => None,
+                            };
+
+                            // Long titles end in an ellipsis short of the
+                            // location tag, with the full text on hover.
+                            let tag_font = egui::TextStyle::Monospace.resolve(ui.style());
+                            let tag = self.location_tag(window).filter(|_| !self.compact);
+                            let tag_width = tag.as_ref().map_or(0.0, |tag| {
+                                let galley = ui.fonts(|fonts| {
+                                    fonts.layout_no_wrap(
+                                        tag.clone(),
+                                        tag_font.clone(),
+                                        egui::Color32::PLACEHOLDER,
+                                    )
+                                });
+                                galley.size().x + 2.0 * TAG_MARGIN
+                            });
+                            let icon_width =
+                                icon.map_or(0.0, |_| ICON_SIZE + ui.spacing().icon_spacing);
+                            let max_width = ui.available_width()
+                                - 2.0 * ui.spacing().button_padding.x
+                                - icon_width
+                                - tag_width;
+                            let (mut text, cut) =
+                                row_layout_job(ui, window, matched, &label, max_width);
+                            // Only the bundle id can still overflow.
+                            text.wrap = egui::text::TextWrapping::truncate_at_width(max_width);
+                            if self.config.show_bundle_id && !window.bundle_id.is_empty() {
+                                text.append(
+                                    &format!("  {}", window.bundle_id),
+                                    0.0,
+                                    egui::TextFormat::simple(
+                                        egui::TextStyle::Small.resolve(ui.style()),
+                                        ui.visuals().weak_text_color(),
+                                    ),
+                                );
+                            }
+                            let text = ui.fonts(|fonts| fonts.layout_job(text));
+                            let elided = cut
+                                || text.elided
+                                || name_width.is_some_and(|width| {
+                                    prefix.chars().count() + window.name.chars().count() > width
+                                });
+
+                            let button = match icon {
+                                Some(texture) => egui::Button::image_and_text(
+                                    egui::Image::from_texture(texture)
+                                        .fit_to_exact_size(egui::vec2(ICON_SIZE, ICON_SIZE)),
+                                    text,
+                                ),
+                                None => egui::Button::new(text),
+                            }
+                            .fill(if is_selected && bar {
+                                self.accent_color.gamma_multiply(0.3)
+                            } else if is_selected {
+                                self.accent_color
+                            } else {
+                                ui.style().visuals.widgets.inactive.bg_fill
+                            })
+                            .min_size(egui::vec2(ui.available_width(), self.config.item_height));
+
+                            let mut response = ui.add(button);
+                            if is_selected && bar {
+                                let mut edge = response.rect;
+                                edge.set_width(SELECTION_BAR_WIDTH);
+                                ui.painter()
+                                    .rect_filled(edge, 0.0, self.accent_color.to_opaque());
+                            }
+                            if let Some(tag) = tag {
+                                ui.painter().text(
+                                    response.rect.right_center() - egui::vec2(TAG_MARGIN, 0.0),
+                                    egui::Align2::RIGHT_CENTER,
+                                    tag,
+                                    tag_font,
+                                    ui.visuals().weak_text_color(),
+                                );
+                            }
+                            if elided {
+                                response = response
+                                    .on_hover_text(format!("{} | {}", window.name, window.info));
+                            }
+
+                            if is_selected && self.scroll_to_selected {
+                                response.scroll_to_me(None);
+                            }
 
-                            let text = format!("{} | {}", window.name, window.info);
-                            let button =
-                                egui::Button::new(egui::RichText::new(text).monospace())
-                                    .fill(if is_selected {
-                                        egui::Color32::from_rgba_premultiplied(
-                                            70, 130, 180, 200,
-                                        )
-                                    } else {
-                                        ui.style().visuals.widgets.inactive.bg_fill
-                                    })
-                                    .min_size(egui::vec2(ui.available_width(), ITEM_HEIGHT));
-
-                            if ui.add(button).clicked() {
+                            // Without click-to-focus the first click selects, so
+                            // hovering mustn't select ahead of it.
+                            let click_to_focus = self.config.click_to_focus;
+                            if response.hovered() && pointer_moved && click_to_focus {
+                                selected = idx;
+                            }
+
+                            // A double-click's first press already selected
+                            // this row, so its second just focuses it.
+                            if response.double_clicked() || (response.clicked() && click_to_focus) {
                                 selected = idx;
                                 window_to_focus = Some(win_idx);
+                            } else if response.clicked() {
+                                selected = idx;
                             }
                         }
 
                         self.selected_index = Some(selected);
+                        self.scroll_to_selected = false;
 
                         if let Some(idx) = window_to_focus {
                             self.selected_index =
-                                self.filtered_windows.iter().position(|&i| i == idx);
+                                self.filtered_windows.iter().position(|m| m.index == idx);
                             if self.focus_selected_window() {
                                 ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                             }
                         }
                     });
+
+                // Everything below the central panel's content: its margin
+                // and the preview pane.
+                let below_list = ctx.screen_rect().bottom() - ui.max_rect().bottom();
+                let list_height = list.content_size.y.min(self.config.max_list_height);
+                self.fit_height(ctx, list_top + list_height + below_list);
             }
         });
     }
 }
 
+/// Like a shell: ArrowUp on an empty query at the top of the list recalls
+/// the last query, and again steps further back until the recalled one is
+/// edited or Down moves into its results. Only the arrow itself recalls,
+/// not other keys bound to Up such as Shift+Tab or Ctrl+P.
+fn recalls_history(
+    key_press: Option<&(KeyAction, egui::Event)>,
+    query: &str,
+    selected_index: Option<usize>,
+    history_index: Option<usize>,
+) -> bool {
+    let plain_arrow_up = matches!(
+        key_press,
+        Some((KeyAction::Up, egui::Event::Key { key: egui::Key::ArrowUp, modifiers, .. }))
+            if modifiers.is_none()
+    );
+    let at_top = query.is_empty() && selected_index.is_none_or(|index| index == 0);
+    plain_arrow_up && (history_index.is_some() || at_top)
+}
+
+/// Records `current` as the workspace the switcher opened on and returns the
+/// one it last opened on elsewhere. Aerospace doesn't say which workspace was
+/// previous, so only switcher launches are seen.
+fn remember_workspace(current: &str) -> Option<String> {
+    let (last, previous): (String, Option<String>) =
+        cache::load(WORKSPACES_CACHE).unwrap_or_default();
+    let previous = if last.is_empty() || last == current {
+        previous
+    } else {
+        Some(last)
+    };
+    cache::save(WORKSPACES_CACHE, &(current, &previous));
+    previous
+}
+
+/// Splits command output into lines, replacing invalid UTF-8 rather than
+/// dropping the line, so a window with an oddly encoded title still shows.
+fn output_lines(output: &[u8]) -> impl Iterator<Item = String> + '_ {
+    output
+        .split(|&byte| byte == b'\n')
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+}
+
+/// Windows piped in with `--stdin`: tab separated `LIST_WINDOWS_FORMAT`
+/// lines if there's a tab anywhere, otherwise `id | app | title` ones.
+fn read_stdin_windows() -> Vec<WindowInfo> {
+    let mut input = Vec::new();
+    if let Err(e) = std::io::stdin().read_to_end(&mut input) {
+        eprintln!("Failed to read stdin: {}", e);
+    }
+    let with_location = input.contains(&b'\t');
+    parse_windows(output_lines(&input), with_location)
+}
+
+/// Parses `aerospace list-windows` output, skipping lines that don't have
+/// every field.
+/// Aerospace can list a window twice mid-transition; the first one wins.
+fn parse_windows(lines: impl Iterator<Item = String>, with_location: bool) -> Vec<WindowInfo> {
+    let mut seen = HashSet::new();
+    lines
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| WindowInfo::parse(&line, with_location))
+        .filter(|window| seen.insert(window.id.clone()))
+        .collect()
+}
+
+/// Splits an `on_select` template on whitespace and fills in `{id}`, `{name}`,
+/// `{title}`, `{workspace}` and `{monitor}` in each argument. There's no
+/// shell, so a field can't spill into other arguments.
+fn expand_template(template: &str, window: &WindowInfo) -> Vec<String> {
+    template
+        .split_whitespace()
+        .map(|arg| {
+            arg.replace("{id}", &window.id)
+                .replace("{name}", &window.name)
+                .replace("{title}", &window.info)
+                .replace("{workspace}", &window.workspace)
+                .replace("{monitor}", &window.monitor)
+        })
+        .collect()
+}
+
+/// Full details of the selected window, for titles too long for their row.
+fn show_preview(ui: &mut egui::Ui, window: &WindowInfo) {
+    ui.label(egui::RichText::new(&window.name).strong());
+    ui.label(&window.info);
+
+    let mut details = format!("id {}", window.id);
+    if !window.workspace.is_empty() {
+        details.push_str(&format!(" · workspace {}", window.workspace));
+    }
+    if !window.monitor.is_empty() {
+        details.push_str(&format!(" · monitor {}", window.monitor));
+    }
+    ui.label(
+        egui::RichText::new(details)
+            .small()
+            .color(ui.visuals().weak_text_color()),
+    );
+}
+
+/// Scores `windows` against each whitespace separated term of `query`, best
+/// first, dropping weak matches. An empty query lists them all in
+/// `empty_sort` order.
+fn rank_windows(
+    windows: &[WindowInfo],
+    query: &str,
+    field: SearchField,
+    ranking: &Ranking,
+) -> Vec<MatchedWindow> {
+    if query.trim().is_empty() {
+        return browse_order(windows, ranking)
+            .into_iter()
+            .map(MatchedWindow::unmatched)
+            .collect();
+    }
+
+    let query = fold_diacritics(query);
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    let match_text = |text: &str, term: &str| match ranking.config.match_mode {
+        MatchMode::Fuzzy => ranking.matcher.fuzzy_indices(text, term),
+        MatchMode::Substring => substring_indices(text, term, ranking.case_sensitive),
+    };
+    // One term's best hit in the window, name first on ties, then the
+    // title, then the id.
+    let match_term = |window: &WindowInfo, term: &str| {
+        let weigh = |(score, indices): (i64, Vec<usize>), weight: f32| {
+            ((score as f32 * weight) as i64, indices)
+        };
+        let name_match = (field != SearchField::Title)
+            .then(|| match_text(&window.search_name, term))
+            .flatten()
+            .map(|found| weigh(found, ranking.config.name_weight));
+        let info_match = (field != SearchField::App)
+            .then(|| match_text(&window.search_info, term))
+            .flatten()
+            .map(|found| weigh(found, ranking.config.info_weight));
+        let id_score = (field == SearchField::Any)
+            .then(|| match_text(&window.id, term))
+            .flatten()
+            .map(|(score, _)| score * ID_SCORE_PERCENT / 100);
+        let bundle_score = (field == SearchField::Any)
+            .then(|| match_text(&window.bundle_id, term))
+            .flatten()
+            .map(|(score, _)| score * ID_SCORE_PERCENT / 100);
+
+        let mut score = id_score.max(bundle_score);
+        let mut name_indices = Vec::new();
+        let mut info_indices = Vec::new();
+        if let Some((s, indices)) = info_match {
+            if score.is_none_or(|best| s >= best) {
+                score = Some(s);
+                info_indices = indices;
+            }
+        }
+        if let Some((s, indices)) = name_match {
+            if score.is_none_or(|best| s >= best) {
+                score = Some(s);
+                info_indices.clear();
+                name_indices = indices;
+            }
+        }
+        Some((score?, name_indices, info_indices))
+    };
+    let score_window = |idx: usize, window: &WindowInfo| {
+        // Every term has to match, e.g. "chrome invoice" for the Chrome
+        // window titled Invoice; their scores add up.
+        let mut matched = MatchedWindow::unmatched(idx);
+        let mut score = 0;
+        for term in &terms {
+            let (s, name_indices, info_indices) = match_term(window, term)?;
+            score += s;
+            matched.name_indices.extend(name_indices);
+            matched.info_indices.extend(info_indices);
+        }
+        for indices in [&mut matched.name_indices, &mut matched.info_indices] {
+            indices.sort_unstable();
+            indices.dedup();
+        }
+        // Initials like "gd" for "Google Docs" beat mid-word hits.
+        let word_starts = word_start_hits(&window.name, &matched.name_indices)
+            + word_start_hits(&window.info, &matched.info_indices);
+        let score = score + word_starts as i64 * WORD_START_BONUS;
+
+        // Windows used often and lately win ambiguous queries. Log scaled
+        // and capped so heavy use nudges close matches rather than
+        // outranking better ones.
+        let usage = (FRECENCY_BOOST_SCALE * ranking.frecency.score(&window.id).ln_1p())
+            .min(FRECENCY_MAX_BOOST);
+        let boost = (ranking.config.frecency_weight * usage) as i64;
+        Some((matched, score, boost))
+    };
+    let score_windows = |offset: usize, part: &[WindowInfo]| -> Vec<(MatchedWindow, i64, i64)> {
+        part.iter()
+            .enumerate()
+            .filter_map(|(i, window)| score_window(offset + i, window))
+            .collect()
+    };
+    // Long lists are scored in chunks across threads. The chunks are joined
+    // back in order, so the stable sort below sees what a serial pass would.
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut scored = if windows.len() < PARALLEL_MIN_WINDOWS || threads == 1 {
+        score_windows(0, windows)
+    } else {
+        let chunk_len = windows.len().div_ceil(threads);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = windows
+                .chunks(chunk_len)
+                .enumerate()
+                .map(|(chunk, part)| {
+                    let score_windows = &score_windows;
+                    scope.spawn(move || score_windows(chunk * chunk_len, part))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    };
+
+    // Ties are broken by name then title so equal scores don't jitter.
+    scored.sort_by_cached_key(|(matched, score, boost)| {
+        let window = &windows[matched.index];
+        (
+            std::cmp::Reverse(score + boost),
+            window.name.to_lowercase(),
+            window.info.to_lowercase(),
+        )
+    });
+
+    // Relative to the best match, so a precise query keeps its few
+    // strong hits while a vague one sheds the long tail. Only how well the
+    // query matched counts here, not the frecency boost.
+    if let Some(best) = scored.iter().map(|&(_, score, _)| score).max() {
+        let cutoff = ranking
+            .config
+            .min_score
+            .max((best as f32 * ranking.config.min_score_ratio) as i64);
+        scored.retain(|&(_, score, _)| score >= cutoff);
+    }
+    scored.into_iter().map(|(matched, _, _)| matched).collect()
+}
+
+/// The order with no query, per `empty_sort`.
+fn browse_order(windows: &[WindowInfo], ranking: &Ranking) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..windows.len()).collect();
+    match ranking.config.empty_sort {
+        EmptySort::Aerospace => {}
+        EmptySort::Mru => {
+            let mru_rank: HashMap<&str, usize> = ranking
+                .mru
+                .iter()
+                .enumerate()
+                .map(|(rank, id)| (id.as_str(), rank))
+                .collect();
+            indices.sort_by_key(|&idx| {
+                mru_rank
+                    .get(windows[idx].id.as_str())
+                    .copied()
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        EmptySort::Workspace => {
+            // Numbered workspaces first, in numeric order so 10 follows 9.
+            indices.sort_by_cached_key(|&idx| {
+                let window = &windows[idx];
+                let workspace = match window.workspace.parse::<u64>() {
+                    Ok(number) => (0, number, String::new()),
+                    Err(_) => (1, 0, window.workspace.to_lowercase()),
+                };
+                (workspace, window.name.to_lowercase())
+            });
+        }
+    }
+    indices
+}
+
+/// Strips accents so "cafe" finds "Café". Works char by char, keeping the
+/// char count (and thus match positions) identical to the original text.
+fn fold_diacritics(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            let mut decomposed = c.nfd();
+            let base = decomposed.next().unwrap_or(c);
+            if decomposed.any(is_combining_mark) && !is_combining_mark(base) {
+                base
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Substring mode's stand-in for `fuzzy_indices`: matches starting earlier
+/// score higher.
+fn substring_indices(text: &str, query: &str, case_sensitive: bool) -> Option<(i64, Vec<usize>)> {
+    // Char by char, so positions in the folded text are positions in `text`.
+    let fold = |s: &str| -> String {
+        if case_sensitive {
+            s.to_string()
+        } else {
+            s.chars()
+                .map(|c| c.to_lowercase().next().unwrap_or(c))
+                .collect()
+        }
+    };
+    let haystack = fold(text);
+    let needle = fold(query);
+    let start = haystack.find(&needle)?;
+    let indices = char_indices_in(&haystack, start..start + needle.len());
+    let position = indices.first().copied().unwrap_or(0) as i64;
+    Some(((SUBSTRING_MAX_SCORE - position).max(1), indices))
+}
+
+/// How many of the matched char positions start a word: the first char,
+/// one after a space or punctuation, or a camelCase hump.
+fn word_start_hits(text: &str, indices: &[usize]) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    indices
+        .iter()
+        .filter(|&&i| {
+            let Some(&c) = chars.get(i) else {
+                return false;
+            };
+            match i.checked_sub(1).map(|prev| chars[prev]) {
+                None => true,
+                Some(prev) => {
+                    (!prev.is_alphanumeric() && c.is_alphanumeric())
+                        || (prev.is_lowercase() && c.is_uppercase())
+                }
+            }
+        })
+        .count()
+}
+
+/// Converts a byte range of `text` into the char positions it covers.
+fn char_indices_in(text: &str, range: std::ops::Range<usize>) -> Vec<usize> {
+    text.char_indices()
+        .enumerate()
+        .filter(|(_, (byte, _))| range.contains(byte))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// How `row_layout_job` draws a row's label.
+struct RowLabel<'a> {
+    /// For matched chars.
+    highlight: egui::Color32,
+    /// Leave out the title.
+    compact: bool,
+    /// Before the name, e.g. the checkmark of a marked row.
+    prefix: &'a str,
+    /// Pad or cut the prefix and name to this many chars so titles line up.
+    name_width: Option<usize>,
+    /// Instead of the text color, for the name only.
+    name_color: Option<egui::Color32>,
+}
+
+/// Builds the `prefix name | info` row label, or just `prefix name` when
+/// `compact`, cut to `max_width` with an ellipsis. Also returns whether it
+/// was cut.
+fn row_layout_job(
+    ui: &egui::Ui,
+    window: &WindowInfo,
+    matched: &MatchedWindow,
+    label: &RowLabel,
+    max_width: f32,
+) -> (egui::text::LayoutJob, bool) {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let text_color = ui.visuals().text_color();
+    let name_color = label.name_color.unwrap_or(text_color);
+
+    let mut chars: Vec<(char, egui::Color32)> = Vec::new();
+    let mut append_field = |text: &str, indices: &[usize], color: egui::Color32| {
+        for (i, c) in text.chars().enumerate() {
+            let color = if indices.binary_search(&i).is_ok() {
+                label.highlight
+            } else {
+                color
+            };
+            chars.push((c, color));
+        }
+    };
+    let prefix = label.prefix;
+    append_field(prefix, &[], text_color);
+    match label
+        .name_width
+        .map(|width| width.saturating_sub(prefix.chars().count()))
+    {
+        Some(width) if window.name.chars().count() > width => {
+            let cut: String = window.name.chars().take(width.saturating_sub(1)).collect();
+            append_field(&cut, &matched.name_indices, name_color);
+            append_field("…", &[], name_color);
+        }
+        Some(width) => {
+            append_field(&window.name, &matched.name_indices, name_color);
+            append_field(
+                &" ".repeat(width - window.name.chars().count()),
+                &[],
+                name_color,
+            );
+        }
+        None => append_field(&window.name, &matched.name_indices, name_color),
+    }
+    if !label.compact {
+        append_field(" | ", &[], text_color);
+        append_field(&window.info, &matched.info_indices, text_color);
+    }
+
+    let text: String = chars.iter().map(|&(c, _)| c).collect();
+    let kept = fitting_chars(ui, &text, &font_id, max_width);
+    let cut = kept < chars.len();
+    if cut {
+        chars.truncate(kept);
+        chars.push(('…', text_color));
+    }
+
+    let mut job = egui::text::LayoutJob::default();
+    for run in chars.chunk_by(|a, b| a.1 == b.1) {
+        let run_text: String = run.iter().map(|&(c, _)| c).collect();
+        job.append(
+            &run_text,
+            0.0,
+            egui::TextFormat::simple(font_id.clone(), run[0].1),
+        );
+    }
+    (job, cut)
+}
+
+/// How many leading chars of `text` fit in `max_width`, leaving room for an
+/// ellipsis if not all do. Cut back to a grapheme boundary so combining
+/// marks and emoji sequences aren't split.
+fn fitting_chars(ui: &egui::Ui, text: &str, font_id: &egui::FontId, max_width: f32) -> usize {
+    let layout = |text: String| {
+        ui.fonts(|fonts| fonts.layout_no_wrap(text, font_id.clone(), egui::Color32::PLACEHOLDER))
+    };
+    let galley = layout(text.to_owned());
+    if galley.size().x <= max_width {
+        return text.chars().count();
+    }
+
+    // Galleys have one glyph per char.
+    let limit = max_width - layout("…".to_owned()).size().x;
+    let fit = galley
+        .rows
+        .iter()
+        .flat_map(|row| &row.glyphs)
+        .take_while(|glyph| glyph.pos.x + glyph.advance_width <= limit)
+        .count();
+    let mut kept = 0;
+    for grapheme in text.graphemes(true) {
+        let end = kept + grapheme.chars().count();
+        if end > fit {
+            break;
+        }
+        kept = end;
+    }
+    kept
+}
+
+/// A color of its own for each app name, the same every launch. FNV-1a
+/// rather than std's hasher, whose output may change between releases.
+fn app_color(name: &str, dark_mode: bool) -> egui::Color32 {
+    let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    let hue = (hash % 360) as f32 / 360.0;
+    let value = if dark_mode { 0.9 } else { 0.55 };
+    egui::ecolor::Hsva::new(hue, 0.55, value, 1.0).into()
+}
+
+/// Refocuses the window from before the last switch, remembering the one
+/// it leaves so a second `--back` returns to it.
+fn focus_back(aerospace_path: &str) -> Result<(), String> {
+    let window_id: String = cache::load(BACK_CACHE).ok_or("no earlier switch to go back from")?;
+    let current = AerospaceWindowSwitcher::fetch_focused_window(aerospace_path);
+    Action::Focus { window_id }
+        .run(aerospace_path)
+        .map_err(|e| format!("Failed to execute aerospace command: {}", e))?;
+    if let Some(current) = current {
+        cache::save(BACK_CACHE, &current);
+    }
+    Ok(())
+}
+
 fn main() {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("aerospace-window-switcher: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        print!("{}", cli::USAGE);
+        return;
+    }
+    if args.version {
+        println!("aerospace-window-switcher {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    log::set_verbose(args.verbose);
+    let config = Config::from_env();
+    if args.back {
+        if let Err(e) = focus_back(&config.aerospace_path) {
+            eprintln!("aerospace-window-switcher: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if config.cycle_on_relaunch && cycle::forward_press() {
+        return;
+    }
+    // Read before the window opens rather than blocking its first frame.
+    let stdin_windows = args.stdin.then(read_stdin_windows);
+    let aerospace_path = config.aerospace_path.clone();
+    let print_only = args.print;
+    let pending_action = Arc::new(Mutex::new(None));
+    let app_pending_action = pending_action.clone();
+
+    let mut viewport = egui::ViewportBuilder::default();
+    let position = match config.spawn_at {
+        SpawnAt::Saved => cache::load::<(f32, f32)>(POSITION_CACHE),
+        SpawnAt::Cursor => cursor::window_position(config.window_width, config.window_height),
+        SpawnAt::Center => None,
+    };
+    if let Some((x, y)) = position {
+        viewport = viewport.with_position([x, y]);
+    }
+
     let native_options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([500.0, 400.0])
+        viewport: viewport
+            .with_inner_size([config.window_width, config.window_height])
             .with_resizable(false)
             .with_decorations(false)
             .with_transparent(true)
             .with_always_on_top()
             .with_active(false)
-            .with_visible(true),
+            .with_visible(false),
+        centered: matches!(config.spawn_at, SpawnAt::Center),
         ..Default::default()
     };
 
@@ -287,19 +2609,276 @@ fn main() {
         "Aerospace Window Switcher",
         native_options,
         Box::new(|cc| {
-            let mut style = (*cc.egui_ctx.style()).clone();
-            style.visuals.window_shadow.blur = 8;
-            style.visuals.widgets.inactive.bg_fill = egui::Color32::TRANSPARENT;
-            style.visuals.widgets.hovered.bg_fill =
-                egui::Color32::from_rgba_premultiplied(60, 60, 60, 180);
-            style.visuals.widgets.active.bg_fill =
-                egui::Color32::from_rgba_premultiplied(80, 80, 80, 180);
-            style.visuals.widgets.noninteractive.bg_fill = egui::Color32::TRANSPARENT;
-            style.visuals.override_text_color = Some(egui::Color32::from_rgb(220, 220, 220));
-            style.visuals.panel_fill = egui::Color32::TRANSPARENT;
-            style.visuals.window_fill = egui::Color32::TRANSPARENT;
-            cc.egui_ctx.set_style(style);
-            Ok(Box::new(AerospaceWindowSwitcher::default()))
+            args.theme.apply(&cc.egui_ctx, config.background_opacity);
+            cc.egui_ctx.style_mut(|style| {
+                style.text_styles.insert(
+                    egui::TextStyle::Monospace,
+                    egui::FontId::monospace(config.font_size),
+                );
+            });
+            Ok(Box::new(AerospaceWindowSwitcher::new(
+                config,
+                args,
+                stdin_windows,
+                app_pending_action,
+            )))
         }),
     );
+
+    let pending_action = pending_action.lock().unwrap().take();
+    if let Some(action) = pending_action {
+        if print_only {
+            for window_id in action.window_ids() {
+                println!("{}", window_id);
+            }
+        } else if let Err(e) = action.run(&aerospace_path) {
+            eprintln!("Failed to execute aerospace command: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> impl Iterator<Item = String> + '_ {
+        text.lines().map(String::from)
+    }
+
+    #[test]
+    fn parse_windows_reads_well_formed_line() {
+        let windows = parse_windows(lines("42\t2\t1\tcom.apple.Safari\tSafari\tApple"), true);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].id, "42");
+        assert_eq!(windows[0].name, "Safari");
+        assert_eq!(windows[0].info, "Apple");
+    }
+
+    #[test]
+    fn parse_windows_skips_blank_lines() {
+        let windows = parse_windows(
+            lines("\n1\t1\t1\ta.a\tA\ta\n   \n2\t1\t1\tb.b\tB\tb\n"),
+            true,
+        );
+        assert_eq!(windows.len(), 2);
+    }
+
+    #[test]
+    fn parse_windows_drops_lines_missing_fields() {
+        assert!(parse_windows(lines("1\t1\t1\tA"), true).is_empty());
+        assert!(parse_windows(lines("1 | A"), false).is_empty());
+    }
+
+    #[test]
+    fn parse_windows_trims_fields() {
+        let windows = parse_windows(lines(" 7  | Terminal   |  ~/src  "), false);
+        assert_eq!(windows[0].id, "7");
+        assert_eq!(windows[0].name, "Terminal");
+        assert_eq!(windows[0].info, "~/src");
+        assert_eq!(windows[0].workspace, "");
+    }
+
+    #[test]
+    fn parse_windows_drops_duplicate_ids() {
+        let windows = parse_windows(
+            lines("5\t1\t1\ta.a\tA\tfirst\n5\t1\t1\ta.a\tA\tsecond"),
+            true,
+        );
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].info, "first");
+    }
+
+    fn ranked(text: &str, query: &str) -> Vec<usize> {
+        let mut windows = parse_windows(lines(text), false);
+        windows.iter_mut().for_each(WindowInfo::prepare_search);
+        let config = Config::default();
+        let frecency = Frecency::default();
+        let matcher = SkimMatcherV2::default();
+        let ranking = Ranking {
+            config: &config,
+            mru: &[],
+            frecency: &frecency,
+            matcher: &matcher,
+            case_sensitive: false,
+        };
+        rank_windows(&windows, query, SearchField::Any, &ranking)
+            .into_iter()
+            .map(|matched| matched.index)
+            .collect()
+    }
+
+    const WINDOWS: &str = "1 | Safari | Apple\n2 | Terminal | ~/src\n3 | Slack | general";
+
+    #[test]
+    fn rank_windows_empty_query_keeps_order() {
+        assert_eq!(ranked(WINDOWS, ""), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn rank_windows_matches_info() {
+        assert_eq!(ranked(WINDOWS, "general"), vec![2]);
+    }
+
+    #[test]
+    fn rank_windows_puts_better_match_first() {
+        let windows = "1 | Sublime Text | notes\n2 | Slack | general";
+        assert_eq!(ranked(windows, "slack"), vec![1]);
+        assert_eq!(ranked(windows, "sl")[0], 1);
+    }
+
+    #[test]
+    fn rank_windows_requires_every_term() {
+        let windows = "1 | Google Chrome | Invoice\n2 | Google Chrome | Inbox\n3 | Mail | Invoice";
+        assert_eq!(ranked(windows, "chrome invoice"), vec![0]);
+        assert_eq!(ranked(windows, "  "), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn rank_windows_is_deterministic_for_long_lists() {
+        let windows: String = (0..PARALLEL_MIN_WINDOWS * 2)
+            .map(|i| format!("{} | App{} | title {}\n", i, i % 7, i % 10))
+            .collect();
+        let ranks = ranked(&windows, "app3 title");
+        assert_eq!(ranks, ranked(&windows, "app3 title"));
+        // Equal scores fall back to the title, and equal titles keep list order.
+        let mut expected: Vec<usize> = (0..PARALLEL_MIN_WINDOWS * 2)
+            .filter(|i| i % 7 == 3)
+            .collect();
+        expected.sort_by_key(|&i| (i % 10, i));
+        assert_eq!(ranks, expected);
+    }
+
+    #[test]
+    fn rank_windows_drops_non_matches() {
+        assert!(ranked(WINDOWS, "zzz").is_empty());
+        assert!(!ranked(WINDOWS, "term").contains(&0));
+    }
+
+    #[test]
+    fn parse_windows_drops_non_numeric_ids() {
+        let windows = parse_windows(lines(" | Safari | Apple\n--all | A | a\n3 | B | b"), false);
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].id, "3");
+    }
+
+    #[test]
+    fn parse_windows_keeps_invalid_utf8_titles() {
+        let output = b"1\t1\t1\ts.s\tSafari\tCaf\xe9 menu\n2\t1\t1\tm.m\tMail\tInbox\n";
+        let windows = parse_windows(output_lines(output), true);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].info, "Caf\u{fffd} menu");
+    }
+
+    /// Stands in for aerospace: lists two windows and appends every other
+    /// command it's given to `calls` next to itself.
+    #[cfg(unix)]
+    const FAKE_AEROSPACE: &str = r#"#!/bin/sh
+case "$1 $2" in
+  "list-windows --focused") echo 1 ;;
+  "list-windows --all")
+    if [ "$4" = "%{window-id}" ]; then
+      printf '1\n2\n'
+    else
+      printf '1\t1\t1\tcom.apple.Safari\tSafari\tApple\n'
+      printf '2\t1\t1\tcom.apple.Terminal\tTerminal\t~/src\n'
+    fi ;;
+  "list-workspaces --focused") echo 1 ;;
+  *) echo "$@" >> "$(dirname "$0")/calls" ;;
+esac
+"#;
+
+    /// Removes the directory when the test ends, passed or not.
+    struct TempDir(std::path::PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn selecting_a_row_focuses_it_through_aerospace() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDir(std::env::temp_dir().join(format!("aws-test-{}", std::process::id())));
+        let dir = &temp.0;
+        std::fs::create_dir_all(dir).unwrap();
+        let script = dir.join("aerospace");
+        std::fs::write(&script, FAKE_AEROSPACE).unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        // Keeps the MRU and other caches out of the real home.
+        cache::set_dir(dir.join("cache"));
+
+        let config = Config {
+            aerospace_path: script.display().to_string(),
+            ..Config::default()
+        };
+        let pending_action = Arc::new(Mutex::new(None));
+        let mut switcher =
+            AerospaceWindowSwitcher::new(config, Args::default(), None, pending_action.clone());
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let fetched = loop {
+            if let Some(fetched) = switcher.windows_shared.lock().unwrap().take() {
+                break fetched;
+            }
+            assert!(std::time::Instant::now() < deadline, "fetch timed out");
+            std::thread::sleep(FETCH_POLL_INTERVAL);
+        };
+        switcher.set_windows(fetched.windows.unwrap());
+
+        switcher.search_query = "term".to_string();
+        switcher.filter_windows();
+        assert!(switcher.focus_selected_window());
+        let action = pending_action.lock().unwrap().take().unwrap();
+        action.run(&switcher.config.aerospace_path).unwrap();
+
+        let calls = std::fs::read_to_string(dir.join("calls")).unwrap();
+        assert_eq!(calls, "focus --window-id 2\n");
+    }
+
+    fn up_press(key: egui::Key, modifiers: egui::Modifiers) -> (KeyAction, egui::Event) {
+        let event = egui::Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        };
+        (KeyAction::Up, event)
+    }
+
+    #[test]
+    fn recalls_history_only_on_plain_arrow_up_at_the_top() {
+        let up = up_press(egui::Key::ArrowUp, egui::Modifiers::NONE);
+        assert!(recalls_history(Some(&up), "", None, None));
+        assert!(recalls_history(Some(&up), "", Some(0), None));
+        // Moving back up the list after Down.
+        assert!(!recalls_history(Some(&up), "", Some(2), None));
+        assert!(!recalls_history(Some(&up), "term", Some(0), None));
+        // An unedited recalled query steps further back.
+        assert!(recalls_history(Some(&up), "term", Some(0), Some(0)));
+
+        let ctrl_p = up_press(egui::Key::P, egui::Modifiers::CTRL);
+        let shift_up = up_press(egui::Key::ArrowUp, egui::Modifiers::SHIFT);
+        assert!(!recalls_history(Some(&ctrl_p), "", None, None));
+        assert!(!recalls_history(Some(&shift_up), "", None, None));
+        assert!(!recalls_history(None, "", None, None));
+    }
+
+    #[test]
+    fn parse_keeps_pipes_in_title() {
+        let window = WindowInfo::parse(
+            "42\t2\t1\tcom.microsoft.VSCode\tCode\tFoo | Bar — Editor",
+            true,
+        )
+        .unwrap();
+        assert_eq!(window.id, "42");
+        assert_eq!(window.workspace, "2");
+        assert_eq!(window.monitor, "1");
+        assert_eq!(window.bundle_id, "com.microsoft.VSCode");
+        assert_eq!(window.name, "Code");
+        assert_eq!(window.info, "Foo | Bar — Editor");
+    }
 }
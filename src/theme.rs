@@ -0,0 +1,68 @@
+use eframe::egui;
+
+/// The switcher floats over the wallpaper, so each theme needs its own
+/// palette to stay readable.
+#[derive(Clone, Copy, Default)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::Dark),
+            "light" => Some(Self::Light),
+            _ => None,
+        }
+    }
+
+    /// `background_opacity` backs the panel with the theme's base color so it
+    /// stays readable over busy wallpapers; 0 keeps it fully transparent.
+    pub fn apply(self, ctx: &egui::Context, background_opacity: u8) {
+        let mut style = (*ctx.style()).clone();
+        match self {
+            Self::Dark => {
+                style.visuals = egui::Visuals::dark();
+                style.visuals.widgets.hovered.bg_fill =
+                    egui::Color32::from_rgba_premultiplied(60, 60, 60, 180);
+                style.visuals.widgets.active.bg_fill =
+                    egui::Color32::from_rgba_premultiplied(80, 80, 80, 180);
+                style.visuals.override_text_color = Some(egui::Color32::from_rgb(220, 220, 220));
+            }
+            Self::Light => {
+                style.visuals = egui::Visuals::light();
+                style.visuals.widgets.hovered.bg_fill = egui::Color32::from_black_alpha(30);
+                style.visuals.widgets.active.bg_fill = egui::Color32::from_black_alpha(50);
+                style.visuals.override_text_color = Some(egui::Color32::from_rgb(30, 30, 30));
+            }
+        }
+        style.visuals.window_shadow.blur = 8;
+        style.visuals.widgets.inactive.bg_fill = egui::Color32::TRANSPARENT;
+        style.visuals.widgets.noninteractive.bg_fill = egui::Color32::TRANSPARENT;
+        style.visuals.panel_fill = match self {
+            Self::Dark => egui::Color32::from_black_alpha(background_opacity),
+            Self::Light => egui::Color32::from_white_alpha(background_opacity),
+        };
+        style.visuals.window_fill = egui::Color32::TRANSPARENT;
+        ctx.set_style(style);
+    }
+
+    /// Fill for the selected row unless `accent_color` overrides it.
+    pub fn selection_fill(self) -> egui::Color32 {
+        match self {
+            Self::Dark => egui::Color32::from_rgba_premultiplied(70, 130, 180, 200),
+            Self::Light => egui::Color32::from_rgba_unmultiplied(70, 130, 180, 110),
+        }
+    }
+
+    /// Color for matched characters on the selected row, which sit on top
+    /// of the selection fill rather than the background.
+    pub fn selected_highlight(self) -> egui::Color32 {
+        match self {
+            Self::Dark => egui::Color32::WHITE,
+            Self::Light => egui::Color32::BLACK,
+        }
+    }
+}